@@ -4,9 +4,9 @@ use ray::float4::Float4;
 use ray::matrix::{rotate_x, rotate_z, scale, translate, view_transform, Matrix};
 use ray::object::{Material, Object, PointLight, Shape};
 use ray::pattern::{Pattern, PatternKind};
+use ray::util::consts::PI;
 use ray::world::World;
 
-use std::f64::consts::PI;
 use std::path::Path;
 
 const CANVAS_WIDTH: usize = 3840 / 2;
@@ -20,6 +20,7 @@ fn main() {
             kind: PatternKind::Stripe(Colour::white(), Colour::black()),
             // transform: Matrix::identity(4),
             transform: rotate_z(PI / 4.0),
+            jitter: None,
         }),
         ..Default::default()
     };
@@ -27,13 +28,14 @@ fn main() {
         shape: Shape::Plane,
         // transform: Matrix::identity(4),
         // transform: rotate_z(PI / 4.0),
-        transform: Matrix::identity(4),
+        transform: Matrix::identity(4).into(),
         material: background_material,
+        ..Default::default()
     };
 
     let middle = Object {
         shape: Shape::Sphere,
-        transform: translate(-0.5, 1.0, 0.5),
+        transform: translate(-0.5, 1.0, 0.5).into(),
         material: Material {
             colour: Colour::new(1.0, 0.49, 0.0),
             diffuse: 0.7,
@@ -42,13 +44,15 @@ fn main() {
             pattern: Some(Pattern {
                 kind: PatternKind::Ring(Colour::new(1.0, 0.0, 0.0), Colour::new(0.0, 0.0, 1.0)),
                 transform: rotate_x(PI / 3.0) * scale(0.25, 0.75, 0.8),
+                jitter: None,
             }),
             ..Default::default()
         },
+        ..Default::default()
     };
     let right = Object {
         shape: Shape::Sphere,
-        transform: translate(1.5, 0.5, -0.5) * scale(0.5, 0.5, 0.5),
+        transform: (translate(1.5, 0.5, -0.5) * scale(0.5, 0.5, 0.5)).into(),
         material: Material {
             colour: Colour::new(0.51, 0.75, 0.06),
             // diffuse: 0.7,
@@ -56,13 +60,15 @@ fn main() {
             pattern: Some(Pattern {
                 kind: PatternKind::Gradient(Colour::new(1.0, 1.0, 0.0), Colour::new(1.0, 0.0, 1.0)),
                 transform: scale(1.0, 2.0, 3.0),
+                jitter: None,
             }),
             ..Default::default()
         },
+        ..Default::default()
     };
     let left = Object {
         shape: Shape::Sphere,
-        transform: translate(-1.5, 0.33, -0.75) * scale(0.33, 0.33, 0.33),
+        transform: (translate(-1.5, 0.33, -0.75) * scale(0.33, 0.33, 0.33)).into(),
         material: Material {
             colour: Colour::new(0.78, 0.28, 0.96),
             // diffuse: 0.7,
@@ -70,9 +76,11 @@ fn main() {
             pattern: Some(Pattern {
                 kind: PatternKind::Checkers(Colour::new(0.0, 1.0, 0.0), Colour::new(0.0, 1.0, 1.0)),
                 transform: translate(1.0, 2.0, 3.0),
+                jitter: None,
             }),
             ..Default::default()
         },
+        ..Default::default()
     };
 
     let world = World {
@@ -81,6 +89,8 @@ fn main() {
             colour: Colour::white(),
         },
         objects: vec![floor, middle, left, right],
+        fog: None,
+        environment: None,
     };
 
     let camera = Camera::new(
@@ -94,6 +104,9 @@ fn main() {
         ),
     );
 
-    let image = camera.render(world);
-    image.to_file(Path::new("images/chapter10.ppm")).unwrap();
+    let (image, stats) = camera.render_with_stats(world);
+    println!("{stats}");
+    image
+        .to_file_binary(Path::new("images/chapter10.ppm"))
+        .unwrap();
 }
@@ -4,9 +4,9 @@ use ray::float4::Float4;
 use ray::matrix::{rotate_x, rotate_z, scale, translate, view_transform, Matrix};
 use ray::object::{Material, Object, PointLight, Shape};
 use ray::pattern::{Pattern, PatternKind};
+use ray::util::consts::PI;
 use ray::world::World;
 
-use std::f64::consts::PI;
 use std::path::Path;
 
 const CANVAS_WIDTH: usize = 3840 / 2;
@@ -21,6 +21,7 @@ fn main() {
             kind: PatternKind::Stripe(Colour::white(), Colour::black()),
             // transform: Matrix::identity(4),
             transform: rotate_z(PI / 4.0),
+            jitter: None,
         }),
         ..Default::default()
     };
@@ -28,13 +29,14 @@ fn main() {
         shape: Shape::Plane,
         // transform: Matrix::identity(4),
         // transform: rotate_z(PI / 4.0),
-        transform: Matrix::identity(4),
+        transform: Matrix::identity(4).into(),
         material: background_material,
+        ..Default::default()
     };
 
     let middle = Object {
         shape: Shape::Sphere,
-        transform: translate(-0.5, 1.0, 0.5),
+        transform: translate(-0.5, 1.0, 0.5).into(),
         material: Material {
             colour: Colour::new(1.0, 0.49, 0.0),
             diffuse: 0.7,
@@ -46,13 +48,15 @@ fn main() {
             pattern: Some(Pattern {
                 kind: PatternKind::Ring(Colour::new(1.0, 0.0, 0.0), Colour::new(0.0, 0.0, 1.0)),
                 transform: rotate_x(PI / 3.0) * scale(0.25, 0.75, 0.8),
+                jitter: None,
             }),
             ..Default::default()
         },
+        ..Default::default()
     };
     let right = Object {
         shape: Shape::Sphere,
-        transform: translate(1.5, 0.5, -0.5) * scale(0.5, 0.5, 0.5),
+        transform: (translate(1.5, 0.5, -0.5) * scale(0.5, 0.5, 0.5)).into(),
         material: Material {
             colour: Colour::new(0.51, 0.75, 0.06),
             // diffuse: 0.7,
@@ -60,13 +64,15 @@ fn main() {
             pattern: Some(Pattern {
                 kind: PatternKind::Gradient(Colour::new(1.0, 1.0, 0.0), Colour::new(1.0, 0.0, 1.0)),
                 transform: scale(1.0, 2.0, 3.0),
+                jitter: None,
             }),
             ..Default::default()
         },
+        ..Default::default()
     };
     let left = Object {
         shape: Shape::Sphere,
-        transform: translate(-1.5, 0.33, -0.75) * scale(0.33, 0.33, 0.33),
+        transform: (translate(-1.5, 0.33, -0.75) * scale(0.33, 0.33, 0.33)).into(),
         material: Material {
             colour: Colour::new(0.78, 0.28, 0.96),
             // diffuse: 0.7,
@@ -74,9 +80,11 @@ fn main() {
             pattern: Some(Pattern {
                 kind: PatternKind::Checkers(Colour::new(0.0, 1.0, 0.0), Colour::new(0.0, 1.0, 1.0)),
                 transform: translate(1.0, 2.0, 3.0),
+                jitter: None,
             }),
             ..Default::default()
         },
+        ..Default::default()
     };
 
     let world = World {
@@ -85,6 +93,8 @@ fn main() {
             colour: Colour::white(),
         },
         objects: vec![floor, middle, left, right],
+        fog: None,
+        environment: None,
     };
 
     let camera = Camera::new(
@@ -98,6 +108,26 @@ fn main() {
         ),
     );
 
-    let image = camera.render(world);
-    image.to_file(Path::new("images/chapter11_2.ppm")).unwrap();
+    // `--inspect <x> <y> [--json]` prints the ray tree behind one pixel instead
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if let Some(i) = args.iter().position(|a| a == "--inspect") {
+        let coordinate = |offset: usize| {
+            args.get(i + offset)
+                .and_then(|a| a.parse::<usize>().ok())
+                .expect("usage: chapter11 --inspect <x> <y> [--json]")
+        };
+        let node = camera.inspect_pixel(&world, coordinate(1), coordinate(2));
+        if args.iter().any(|a| a == "--json") {
+            println!("{}", node.to_json());
+        } else {
+            print!("{node}");
+        }
+        return;
+    }
+
+    let (image, stats) = camera.render_with_stats(world);
+    println!("{stats}");
+    image
+        .to_file_binary(Path::new("images/chapter11_2.ppm"))
+        .unwrap();
 }
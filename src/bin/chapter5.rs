@@ -1,9 +1,10 @@
 use ray::canvas::Canvas;
 use ray::colour::Colour;
-use ray::float4::Float4;
 use ray::matrix::{scale, shear};
 use ray::object::{Material, Object, Shape};
 use ray::ray::Ray;
+use ray::util::Float;
+use ray::vector::Point3;
 
 use std::path::Path;
 use std::sync::Mutex;
@@ -23,29 +24,27 @@ fn main() {
         // transform: scale(1.0, 0.5, 1.0),
         // transform: scale(0.5, 1.0, 1.0),
         // transform: rotate_z(PI/4.0) * scale(0.5, 1.0, 1.0),
-        transform: shear(1.0, 0.0, 0.0, 0.0, 0.0, 0.0) * scale(0.5, 1.0, 1.0),
+        transform: (shear(1.0, 0.0, 0.0, 0.0, 0.0, 0.0) * scale(0.5, 1.0, 1.0)).into(),
         material: Material::default(),
+        ..Default::default()
     };
 
-    let ray_origin = Float4::new_point(0.0, 0.0, -5.0);
+    let ray_origin = Point3::new(0.0, 0.0, -5.0);
     let wall_z = 10.0;
 
     let wall_size = 7.0;
     let half = wall_size / 2.0;
-    let pixel_size = wall_size / CANVAS_PIXELS as f64;
+    let pixel_size = wall_size / CANVAS_PIXELS as Float;
 
     (0..CANVAS_PIXELS)
         .cartesian_product(0..CANVAS_PIXELS)
         .par_bridge()
         .for_each(|(y, x)| {
-            let world_y = half - pixel_size * y as f64;
-            let world_x = -half + pixel_size * x as f64;
+            let world_y = half - pixel_size * y as Float;
+            let world_x = -half + pixel_size * x as Float;
 
-            let position = Float4::new_point(world_x, world_y, wall_z);
-            let ray = Ray {
-                origin: ray_origin,
-                direction: (position - ray_origin).normalise(),
-            };
+            let position = Point3::new(world_x, world_y, wall_z);
+            let ray = Ray::new(ray_origin, (position - ray_origin).normalise());
 
             if sphere.intersect(&ray).hit().is_some() {
                 let mut canvas = canvas_mutex.lock().unwrap();
@@ -56,6 +55,6 @@ fn main() {
     canvas_mutex
         .lock()
         .unwrap()
-        .to_file(Path::new("images/sphere_silhouette5.ppm"))
+        .to_file_binary(Path::new("images/sphere_silhouette5.ppm"))
         .unwrap();
 }
@@ -4,6 +4,8 @@ use ray::float4::Float4;
 use ray::matrix::Matrix;
 use ray::object::{Material, Object, PointLight, Shape};
 use ray::ray::Ray;
+use ray::util::Float;
+use ray::vector::Point3;
 
 use std::path::Path;
 use std::sync::Mutex;
@@ -18,7 +20,7 @@ fn main() {
 
     let sphere = Object {
         shape: Shape::Sphere,
-        transform: Matrix::identity(4),
+        transform: Matrix::identity(4).into(),
         // scale(1.0, 0.5, 1.0),
         // scale(0.5, 1.0, 1.0),
         // rotate_z(PI/4.0) * scale(0.5, 1.0, 1.0),
@@ -27,6 +29,7 @@ fn main() {
             colour: Colour::new(0.0, 0.2, 1.0),
             ..Default::default()
         },
+        ..Default::default()
     };
 
     let light = PointLight {
@@ -34,30 +37,27 @@ fn main() {
         colour: Colour::new(1.0, 1.0, 1.0),
     };
 
-    let ray_origin = Float4::new_point(0.0, 0.0, -5.0);
+    let ray_origin = Point3::new(0.0, 0.0, -5.0);
     let wall_z = 10.0;
 
     let wall_size = 7.0;
     let half = wall_size / 2.0;
-    let pixel_size = wall_size / CANVAS_PIXELS as f64;
+    let pixel_size = wall_size / CANVAS_PIXELS as Float;
 
     (0..CANVAS_PIXELS)
         .cartesian_product(0..CANVAS_PIXELS)
         .par_bridge()
         .for_each(|(y, x)| {
-            let world_y = half - pixel_size * y as f64;
-            let world_x = -half + pixel_size * x as f64;
+            let world_y = half - pixel_size * y as Float;
+            let world_x = -half + pixel_size * x as Float;
 
-            let position = Float4::new_point(world_x, world_y, wall_z);
-            let ray = Ray {
-                origin: ray_origin,
-                direction: (position - ray_origin).normalise(),
-            };
+            let position = Point3::new(world_x, world_y, wall_z);
+            let ray = Ray::new(ray_origin, (position - ray_origin).normalise());
 
             if let Some(hit) = sphere.intersect(&ray).hit() {
-                let point = ray.position(hit.distance());
+                let point = Float4::from(ray.position(hit.distance()));
                 let normalv = hit.object().normal_at(point);
-                let eyev = ray.direction.scalar_mul(-1.0);
+                let eyev = Float4::from(-ray.direction);
                 let colour = hit.object().lighting(light, point, eyev, normalv, false);
                 let mut canvas = canvas_mutex.lock().unwrap();
                 canvas.write_pixel(x, y, colour);
@@ -67,6 +67,6 @@ fn main() {
     canvas_mutex
         .lock()
         .unwrap()
-        .to_file(Path::new("images/sphere_silhouette_3d.ppm"))
+        .to_file_binary(Path::new("images/sphere_silhouette_3d.ppm"))
         .unwrap();
 }
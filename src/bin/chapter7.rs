@@ -3,9 +3,9 @@ use ray::colour::Colour;
 use ray::float4::Float4;
 use ray::matrix::{rotate_x, rotate_y, scale, translate, view_transform};
 use ray::object::{Material, Object, PointLight, Shape};
+use ray::util::consts::PI;
 use ray::world::World;
 
-use std::f64::consts::PI;
 use std::path::Path;
 
 const CANVAS_WIDTH: usize = 3840;
@@ -19,31 +19,36 @@ fn main() {
     };
     let floor = Object {
         shape: Shape::Sphere,
-        transform: scale(10.0, 0.01, 10.0),
+        transform: scale(10.0, 0.01, 10.0).into(),
         material: background_material.clone(),
+        ..Default::default()
     };
 
     let left_wall = Object {
         shape: Shape::Sphere,
-        transform: translate(0.0, 0.0, 5.0)
+        transform: (translate(0.0, 0.0, 5.0)
             * rotate_y(-PI / 4.0)
             * rotate_x(PI / 2.0)
-            * scale(10.0, 0.01, 10.0),
+            * scale(10.0, 0.01, 10.0))
+        .into(),
         material: background_material.clone(),
+        ..Default::default()
     };
 
     let right_wall = Object {
         shape: Shape::Sphere,
-        transform: translate(0.0, 0.0, 5.0)
+        transform: (translate(0.0, 0.0, 5.0)
             * rotate_y(PI / 4.0)
             * rotate_x(PI / 2.0)
-            * scale(10.0, 0.01, 10.0),
+            * scale(10.0, 0.01, 10.0))
+        .into(),
         material: background_material,
+        ..Default::default()
     };
 
     let middle = Object {
         shape: Shape::Sphere,
-        transform: translate(-0.5, 1.0, 0.5),
+        transform: translate(-0.5, 1.0, 0.5).into(),
         material: Material {
             colour: Colour::new(1.0, 0.49, 0.0),
             diffuse: 0.7,
@@ -51,26 +56,29 @@ fn main() {
             shininess: 50.0,
             ..Default::default()
         },
+        ..Default::default()
     };
     let right = Object {
         shape: Shape::Sphere,
-        transform: translate(1.5, 0.5, -0.5) * scale(0.5, 0.5, 0.5),
+        transform: (translate(1.5, 0.5, -0.5) * scale(0.5, 0.5, 0.5)).into(),
         material: Material {
             colour: Colour::new(0.51, 0.75, 0.06),
             // diffuse: 0.7,
             // specular: 0.3,
             ..Default::default()
         },
+        ..Default::default()
     };
     let left = Object {
         shape: Shape::Sphere,
-        transform: translate(-1.5, 0.33, -0.75) * scale(0.33, 0.33, 0.33),
+        transform: (translate(-1.5, 0.33, -0.75) * scale(0.33, 0.33, 0.33)).into(),
         material: Material {
             colour: Colour::new(0.78, 0.28, 0.96),
             // diffuse: 0.7,
             // specular: 0.3,
             ..Default::default()
         },
+        ..Default::default()
     };
 
     let world = World {
@@ -79,6 +87,8 @@ fn main() {
             colour: Colour::white(),
         },
         objects: vec![floor, left_wall, right_wall, middle, left, right],
+        fog: None,
+        environment: None,
     };
 
     let camera = Camera::new(
@@ -92,6 +102,9 @@ fn main() {
         ),
     );
 
-    let image = camera.render(world);
-    image.to_file(Path::new("images/chapter8_1.ppm")).unwrap();
+    let (image, stats) = camera.render_with_stats(world);
+    println!("{stats}");
+    image
+        .to_file_binary(Path::new("images/chapter8_1.ppm"))
+        .unwrap();
 }
@@ -3,9 +3,9 @@ use ray::colour::Colour;
 use ray::float4::Float4;
 use ray::matrix::{scale, translate, view_transform, Matrix};
 use ray::object::{Material, Object, PointLight, Shape};
+use ray::util::consts::PI;
 use ray::world::World;
 
-use std::f64::consts::PI;
 use std::path::Path;
 
 const CANVAS_WIDTH: usize = 3840 / 8;
@@ -21,13 +21,14 @@ fn main() {
         shape: Shape::Plane,
         // transform: Matrix::identity(4),
         // transform: rotate_z(PI / 4.0),
-        transform: Matrix::identity(4),
+        transform: Matrix::identity(4).into(),
         material: background_material,
+        ..Default::default()
     };
 
     let middle = Object {
         shape: Shape::Sphere,
-        transform: translate(-0.5, 1.0, 0.5),
+        transform: translate(-0.5, 1.0, 0.5).into(),
         material: Material {
             colour: Colour::new(1.0, 0.49, 0.0),
             diffuse: 0.7,
@@ -35,26 +36,29 @@ fn main() {
             shininess: 50.0,
             ..Default::default()
         },
+        ..Default::default()
     };
     let right = Object {
         shape: Shape::Sphere,
-        transform: translate(1.5, 0.5, -0.5) * scale(0.5, 0.5, 0.5),
+        transform: (translate(1.5, 0.5, -0.5) * scale(0.5, 0.5, 0.5)).into(),
         material: Material {
             colour: Colour::new(0.51, 0.75, 0.06),
             // diffuse: 0.7,
             // specular: 0.3,
             ..Default::default()
         },
+        ..Default::default()
     };
     let left = Object {
         shape: Shape::Sphere,
-        transform: translate(-1.5, 0.33, -0.75) * scale(0.33, 0.33, 0.33),
+        transform: (translate(-1.5, 0.33, -0.75) * scale(0.33, 0.33, 0.33)).into(),
         material: Material {
             colour: Colour::new(0.78, 0.28, 0.96),
             // diffuse: 0.7,
             // specular: 0.3,
             ..Default::default()
         },
+        ..Default::default()
     };
 
     let world = World {
@@ -63,6 +67,8 @@ fn main() {
             colour: Colour::white(),
         },
         objects: vec![floor, middle, left, right],
+        fog: None,
+        environment: None,
     };
 
     let camera = Camera::new(
@@ -76,6 +82,9 @@ fn main() {
         ),
     );
 
-    let image = camera.render(world);
-    image.to_file(Path::new("images/chapter9_5.ppm")).unwrap();
+    let (image, stats) = camera.render_with_stats(world);
+    println!("{stats}");
+    image
+        .to_file_binary(Path::new("images/chapter9_5.ppm"))
+        .unwrap();
 }
@@ -0,0 +1,73 @@
+use ray::canvas::Canvas;
+use ray::util::Float;
+
+use std::path::Path;
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: ray-diff <expected> <actual> [--heatmap <file>] [--scale <error>]";
+
+// Exits with 0 for identical images, 1 when they differ and 2 on errors.
+fn main() -> ExitCode {
+    match run() {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(message) => {
+            eprintln!("{message}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run() -> Result<bool, String> {
+    let mut images = vec![];
+    let mut heatmap = None;
+    let mut scale = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--heatmap" => heatmap = Some(args.next().ok_or(USAGE)?),
+            "--scale" => {
+                let value = args.next().ok_or(USAGE)?;
+                scale = Some(
+                    value
+                        .parse::<Float>()
+                        .map_err(|_| format!("bad scale: {value}"))?,
+                );
+            }
+            _ if arg.starts_with("--") => return Err(USAGE.to_string()),
+            _ => images.push(arg),
+        }
+    }
+    let [expected, actual] = &images[..] else {
+        return Err(USAGE.to_string());
+    };
+
+    let read = |path: &str| {
+        Canvas::read_file(Path::new(path)).map_err(|e| format!("cannot read {path}: {e}"))
+    };
+    let (expected, actual) = (read(expected)?, read(actual)?);
+    if (expected.width, expected.height) != (actual.width, actual.height) {
+        return Err(format!(
+            "sizes differ: {}x{} and {}x{}",
+            expected.width, expected.height, actual.width, actual.height
+        ));
+    }
+
+    let result = expected.compare(&actual);
+    println!("MSE        {:.6}", result.mse);
+    println!("PSNR       {:.2} dB", result.psnr);
+    println!("max error  {:.6}", result.max_error);
+    println!("SSIM       {:.6}", result.ssim);
+
+    if let Some(path) = heatmap {
+        // by default the largest difference is drawn white
+        let scale = scale.unwrap_or(result.max_error);
+        expected
+            .heatmap(&actual, scale)
+            .to_file_binary(Path::new(&path))
+            .map_err(|e| format!("cannot write {path}: {e}"))?;
+    }
+
+    Ok(result.max_error == 0.0)
+}
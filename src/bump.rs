@@ -0,0 +1,231 @@
+use std::sync::Arc;
+
+use crate::{
+    canvas::Canvas,
+    float4::Float4,
+    noise::Noise,
+    util::{consts::PI, Float, EPSILON},
+};
+
+const GRADIENT_STEP: Float = 1e-3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UvMapping {
+    Spherical,
+    Planar,
+}
+
+impl UvMapping {
+    pub fn uv(&self, point: Float4) -> (Float, Float) {
+        let (x, y, z) = (point.0[0], point.0[1], point.0[2]);
+        match self {
+            UvMapping::Spherical => {
+                let theta = x.atan2(z);
+                let radius = (x * x + y * y + z * z).sqrt();
+                let phi = (y / radius).acos();
+                let raw_u = theta / (2.0 * PI);
+                (1.0 - (raw_u + 0.5), 1.0 - phi / PI)
+            }
+            UvMapping::Planar => (x - x.floor(), z - z.floor()),
+        }
+    }
+
+    // The direction u increases in at `point`, which has no length at the poles
+    // of a sphere.
+    pub fn tangent(&self, point: Float4) -> Float4 {
+        let (x, z) = (point.0[0], point.0[2]);
+        match self {
+            // u runs against the angle about y
+            UvMapping::Spherical => Float4::new_vector(-z, 0.0, x),
+            UvMapping::Planar => Float4::new_vector(1.0, 0.0, 0.0),
+        }
+    }
+
+    // The direction v increases in at `point`.
+    pub fn bitangent(&self, point: Float4) -> Float4 {
+        let (x, y, z) = (point.0[0], point.0[1], point.0[2]);
+        match self {
+            // towards the north pole along a meridian
+            UvMapping::Spherical => Float4::new_vector(-x * y, x * x + z * z, -z * y),
+            UvMapping::Planar => Float4::new_vector(0.0, 0.0, 1.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Bump {
+    Noise(Noise),
+    NormalMap {
+        image: Arc<Canvas>,
+        mapping: UvMapping,
+    },
+}
+
+impl Bump {
+    // `point` and `normal` are both in object space.
+    pub fn perturb(&self, point: Float4, normal: Float4) -> Float4 {
+        let normal = normal.normalise();
+        match self {
+            Bump::Noise(noise) => {
+                // only the part of the gradient along the surface tilts the
+                // normal, leaning it away from where the noise rises
+                let gradient = noise_gradient(noise, point);
+                let surface_gradient = gradient - normal.scalar_mul(normal.dot(gradient));
+                (normal - surface_gradient.scalar_mul(noise.amplitude)).normalise()
+            }
+            Bump::NormalMap { image, mapping } => {
+                let (u, v) = mapping.uv(point);
+                let x = (u.clamp(0.0, 1.0) * (image.width - 1) as Float).round() as usize;
+                let y = ((1.0 - v.clamp(0.0, 1.0)) * (image.height - 1) as Float).round() as usize;
+                let texel = image.pixel_at(x, y).0;
+
+                let (tangent, bitangent) = frame(*mapping, point, normal);
+                (tangent.scalar_mul(2.0 * texel.0[0] - 1.0)
+                    + bitangent.scalar_mul(2.0 * texel.0[1] - 1.0)
+                    + normal.scalar_mul(2.0 * texel.0[2] - 1.0))
+                .normalise()
+            }
+        }
+    }
+}
+
+fn noise_gradient(noise: &Noise, point: Float4) -> Float4 {
+    let axis = |i: usize| {
+        let mut offset = [0.0; 4];
+        offset[i] = GRADIENT_STEP;
+        let offset = Float4::from_array(offset);
+        (noise.fbm(point + offset) - noise.fbm(point - offset)) / (2.0 * GRADIENT_STEP)
+    };
+    Float4::new_vector(axis(0), axis(1), axis(2))
+}
+
+// Unit tangent and bitangent along the mapping's u and v, made perpendicular to
+// `normal`. Where u has no direction, as at a pole, any tangent will do.
+fn frame(mapping: UvMapping, point: Float4, normal: Float4) -> (Float4, Float4) {
+    let flatten = |v: Float4| v - normal.scalar_mul(normal.dot(v));
+    let mut tangent = flatten(mapping.tangent(point));
+    if tangent.mag() < EPSILON {
+        let up = if normal.0[1].abs() > 0.999 {
+            Float4::new_vector(1.0, 0.0, 0.0)
+        } else {
+            Float4::new_vector(0.0, 1.0, 0.0)
+        };
+        tangent = up.cross(normal);
+    }
+    let tangent = tangent.normalise();
+
+    // keep the handedness of the mapping, whichever way round that is
+    let bitangent = normal.cross(tangent);
+    if bitangent.dot(mapping.bitangent(point)) < 0.0 {
+        (tangent, -bitangent)
+    } else {
+        (tangent, bitangent)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{colour::Colour, util::float_is_eq};
+
+    #[test]
+    fn spherical_uv() {
+        let cases = [
+            (Float4::new_point(0.0, 0.0, -1.0), (0.0, 0.5)),
+            (Float4::new_point(1.0, 0.0, 0.0), (0.25, 0.5)),
+            (Float4::new_point(0.0, 0.0, 1.0), (0.5, 0.5)),
+            (Float4::new_point(-1.0, 0.0, 0.0), (0.75, 0.5)),
+            (Float4::new_point(0.0, 1.0, 0.0), (0.5, 1.0)),
+            (Float4::new_point(0.0, -1.0, 0.0), (0.5, 0.0)),
+        ];
+        for (point, (u, v)) in cases {
+            let (pu, pv) = UvMapping::Spherical.uv(point);
+            assert!(float_is_eq(pu, u) && float_is_eq(pv, v));
+        }
+    }
+
+    #[test]
+    fn planar_uv() {
+        let (u, v) = UvMapping::Planar.uv(Float4::new_point(0.25, 0.0, -1.75));
+        assert!(float_is_eq(u, 0.25));
+        assert!(float_is_eq(v, 0.25));
+    }
+
+    #[test]
+    fn flat_normal_map() {
+        let bump = Bump::NormalMap {
+            image: Arc::new(Canvas::new(4, 4, Colour::new(0.5, 0.5, 1.0))),
+            mapping: UvMapping::Spherical,
+        };
+        let point = Float4::new_point(0.0, 0.0, -1.0);
+        let normal = Float4::new_vector(0.0, 0.0, -1.0);
+        assert_eq!(bump.perturb(point, normal), normal);
+    }
+
+    #[test]
+    fn tilted_normal_map() {
+        let bump = Bump::NormalMap {
+            image: Arc::new(Canvas::new(4, 4, Colour::new(1.0, 0.5, 0.5))),
+            mapping: UvMapping::Planar,
+        };
+        let point = Float4::new_point(0.5, 0.0, 0.5);
+        let normal = Float4::new_vector(0.0, 1.0, 0.0);
+        let perturbed = bump.perturb(point, normal);
+        assert!(float_is_eq(perturbed.mag(), 1.0));
+        assert!(float_is_eq(perturbed.dot(normal), 0.0));
+        // red is along u, which the planar mapping takes from x
+        assert_eq!(perturbed, Float4::new_vector(1.0, 0.0, 0.0));
+
+        // halfway between the flat normal and u, then v
+        let texel = |r, g| Bump::NormalMap {
+            image: Arc::new(Canvas::new(1, 1, Colour::new(r, g, 1.0))),
+            mapping: UvMapping::Planar,
+        };
+        let towards_u = texel(1.0, 0.5).perturb(point, normal);
+        assert_eq!(towards_u, Float4::new_vector(1.0, 1.0, 0.0).normalise());
+        let towards_v = texel(0.5, 1.0).perturb(point, normal);
+        assert_eq!(towards_v, Float4::new_vector(0.0, 1.0, 1.0).normalise());
+    }
+
+    #[test]
+    fn spherical_frame() {
+        let mapping = UvMapping::Spherical;
+        let point = Float4::new_point(0.6, 0.0, -0.8);
+        let normal = point - Float4::origin();
+        let (tangent, bitangent) = frame(mapping, point, normal);
+        // a small step along each changes u and v alone, and in the right direction
+        let (u, v) = mapping.uv(point);
+        let (u1, v1) = mapping.uv(point + tangent.scalar_mul(1e-3));
+        let (u2, v2) = mapping.uv(point + bitangent.scalar_mul(1e-3));
+        assert!(u1 > u && float_is_eq(v1, v));
+        assert!(v2 > v && float_is_eq(u2, u));
+
+        // at the pole there is still a frame
+        let pole = Float4::new_point(0.0, 1.0, 0.0);
+        let (tangent, bitangent) = frame(mapping, pole, pole - Float4::origin());
+        assert!(float_is_eq(tangent.mag(), 1.0) && float_is_eq(bitangent.mag(), 1.0));
+    }
+
+    #[test]
+    fn noise_bump() {
+        let point = Float4::new_point(0.3, 0.4, 0.5);
+        let normal = Float4::new_vector(0.0, 1.0, 0.0);
+
+        let flat = Bump::Noise(Noise::new(9, 0.0, 3));
+        assert_eq!(flat.perturb(point, normal), normal);
+
+        let noise = Noise::new(9, 0.3, 3);
+        let perturbed = Bump::Noise(noise).perturb(point, normal);
+        assert!(float_is_eq(perturbed.mag(), 1.0));
+        assert_ne!(perturbed, normal);
+
+        // the normal leans against the slope of the noise across the surface,
+        // and the slope along the normal makes no difference
+        let [gx, _, gz, _] = noise_gradient(&noise, point).to_array();
+        let downhill = Float4::new_vector(-gx, 0.0, -gz);
+        assert!(perturbed.dot(downhill) > 0.0);
+        let tilt = perturbed.scalar_mul(1.0 / perturbed.dot(normal)) - normal;
+        assert!(float_is_eq(tilt.dot(normal), 0.0));
+        assert_eq!(tilt, downhill.scalar_mul(0.3));
+    }
+}
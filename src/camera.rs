@@ -1,28 +1,165 @@
 use std::sync::Mutex;
+use std::time::Instant;
 
 use crate::{
-    canvas::Canvas, colour::Colour, float4::Float4, matrix::Matrix, ray::Ray, world::World,
+    canvas::Canvas,
+    colour::Colour,
+    compare::heat,
+    inspect::PathNode,
+    matrix::Matrix,
+    ray::Ray,
+    sampling::Rng,
+    stats::{self, RenderStats},
+    util::Float,
+    vector::Point3,
+    world::World,
     REF_RECURSION_LIMIT,
 };
 
 use itertools::Itertools;
 use rayon::prelude::*;
 
+// side of the square blocks of pixels handed to each rayon task
+const TILE_SIZE: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Integrator {
+    Whitted,
+    PathTracer { samples: usize, max_depth: u8 },
+}
+
+// Replaces shading with a view of what the tracer sees at each pixel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DebugView {
+    // world-space shading normal, remapped from [-1, 1] to [0, 1]
+    Normals,
+    // texture coordinates as red and green
+    Uv,
+    // surface or pattern colour with no lighting
+    Albedo,
+    // intersection tests made while shading the pixel; `max` or more is white
+    IntersectionTests { max: u64 },
+    // average recursion depth of the pixel's hits, relative to the integrator's limit
+    RecursionDepth,
+    // how much of the point light reaches the first hit
+    Shadows,
+}
+
+// Auxiliary passes rendered alongside the beauty image, one value per pixel
+// from the primary ray. Pixels where nothing was hit are black. Depth and object
+// id are raw values well above one, so write them as `.pfm` or `.hdr`, or take
+// `normalised` passes for the 8-bit formats.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aovs {
+    // distance from the camera to the hit
+    pub depth: Canvas,
+    // world-space shading normal, remapped from [-1, 1] to [0, 1]
+    pub normal: Canvas,
+    // surface or pattern colour before lighting
+    pub albedo: Canvas,
+    // index into `World::objects`, plus one
+    pub object_id: Canvas,
+    // how much of the point light is blocked, per channel
+    pub shadow: Canvas,
+}
+
+impl Aovs {
+    fn new(width: usize, height: usize) -> Self {
+        let blank = Canvas::new(width, height, Colour::black());
+        Self {
+            depth: blank.clone(),
+            normal: blank.clone(),
+            albedo: blank.clone(),
+            object_id: blank.clone(),
+            shadow: blank,
+        }
+    }
+
+    // Depth and object id scaled so their largest values are white.
+    pub fn normalised(&self) -> Self {
+        let normalise = |canvas: &Canvas| {
+            let max = canvas
+                .pixels
+                .iter()
+                .flatten()
+                .fold(0.0, |m: Float, c| m.max(c.0 .0[0]));
+            let scale = if max > 0.0 { 1.0 / max } else { 1.0 };
+            Canvas {
+                pixels: canvas
+                    .pixels
+                    .iter()
+                    .map(|row| row.iter().map(|c| *c * scale).collect())
+                    .collect(),
+                ..*canvas
+            }
+        };
+        Self {
+            depth: normalise(&self.depth),
+            object_id: normalise(&self.object_id),
+            ..self.clone()
+        }
+    }
+
+    fn write(&mut self, x: usize, y: usize, pixel: &AovPixel) {
+        self.depth.write_pixel(x, y, pixel.depth);
+        self.normal.write_pixel(x, y, pixel.normal);
+        self.albedo.write_pixel(x, y, pixel.albedo);
+        self.object_id.write_pixel(x, y, pixel.object_id);
+        self.shadow.write_pixel(x, y, pixel.shadow);
+    }
+}
+
+// One pixel of each of the `Aovs` passes.
+struct AovPixel {
+    depth: Colour,
+    normal: Colour,
+    albedo: Colour,
+    object_id: Colour,
+    shadow: Colour,
+}
+
+impl AovPixel {
+    // None where the ray hits nothing
+    fn trace(world: &World, ray: &Ray) -> Option<Self> {
+        let hit = world.intersect(ray).hit()?;
+        let grey = |v: Float| Colour::new(v, v, v);
+        let normal = hit.normalv().normalise().0;
+        let index = world
+            .objects
+            .iter()
+            .position(|o| std::ptr::eq(o, hit.object()))
+            .map_or(0.0, |i| (i + 1) as Float);
+        let over_point = hit.over_point();
+
+        Some(Self {
+            depth: grey(hit.distance() * ray.direction.mag()),
+            normal: Colour::new(normal[0], normal[1], normal[2]) * 0.5 + grey(0.5),
+            albedo: hit.object().colour_at(hit.point()),
+            object_id: grey(index),
+            shadow: Colour::white() - world.shadow_transmittance(over_point, world.light.position),
+        })
+    }
+}
+
 pub struct Camera {
     hsize: usize,
     vsize: usize,
-    half_width: f64,
-    half_height: f64,
+    half_width: Float,
+    half_height: Float,
     #[allow(dead_code)]
-    field_of_view: f64,
-    pixel_size: f64,
+    field_of_view: Float,
+    pixel_size: Float,
     transform: Matrix,
+    integrator: Integrator,
+    debug_view: Option<DebugView>,
+    // draw a progress bar on the terminal while rendering
+    progress: bool,
 }
 
 impl Camera {
-    pub fn new(hsize: usize, vsize: usize, field_of_view: f64, transform: Matrix) -> Self {
+    pub fn new(hsize: usize, vsize: usize, field_of_view: Float, transform: Matrix) -> Self {
         let half_view = (field_of_view / 2.0).tan();
-        let aspect = hsize as f64 / vsize as f64;
+        let aspect = hsize as Float / vsize as Float;
 
         let (half_width, half_height) = if aspect >= 1.0 {
             (half_view, half_view / aspect)
@@ -30,7 +167,7 @@ impl Camera {
             (half_view * aspect, half_view)
         };
 
-        let pixel_size = (2.0 * half_width) / hsize as f64;
+        let pixel_size = (2.0 * half_width) / hsize as Float;
 
         Self {
             hsize,
@@ -40,59 +177,217 @@ impl Camera {
             field_of_view,
             pixel_size,
             transform,
+            integrator: Integrator::Whitted,
+            debug_view: None,
+            progress: true,
         }
     }
 
+    pub fn with_integrator(mut self, integrator: Integrator) -> Self {
+        self.integrator = integrator;
+        self
+    }
+
+    pub fn with_debug_view(mut self, debug_view: DebugView) -> Self {
+        self.debug_view = Some(debug_view);
+        self
+    }
+
+    pub fn with_progress(mut self, progress: bool) -> Self {
+        self.progress = progress;
+        self
+    }
+
     pub fn ray_for_pixel(&self, px: usize, py: usize) -> Ray {
-        let xoffset = (px as f64 + 0.5) * self.pixel_size;
-        let yoffset = (py as f64 + 0.5) * self.pixel_size;
+        self.ray_for_subpixel(px, py, 0.5, 0.5)
+    }
+
+    // `dx` and `dy` are offsets within the pixel, in [0, 1)
+    pub fn ray_for_subpixel(&self, px: usize, py: usize, dx: Float, dy: Float) -> Ray {
+        let xoffset = (px as Float + dx) * self.pixel_size;
+        let yoffset = (py as Float + dy) * self.pixel_size;
 
         let world_x = self.half_width - xoffset;
         let world_y = self.half_height - yoffset;
 
-        let pixel: Float4 =
-            self.transform.inverse().unwrap() * Float4::new_point(world_x, world_y, -1.0);
-
-        let origin: Float4 = self.transform.inverse().unwrap() * Float4::origin();
+        let inverse = self.transform.inverse().unwrap();
+        let pixel = &inverse * Point3::new(world_x, world_y, -1.0);
+        let origin = &inverse * Point3::origin();
         let direction = (pixel - origin).normalise();
 
-        Ray { origin, direction }
+        Ray::new(origin, direction)
+    }
+
+    fn colour_for_pixel(&self, world: &World, x: usize, y: usize) -> Colour {
+        match self.debug_view {
+            Some(view) => self.debug_colour_for_pixel(world, view, x, y),
+            None => self.shade_pixel(world, x, y),
+        }
+    }
+
+    fn debug_colour_for_pixel(&self, world: &World, view: DebugView, x: usize, y: usize) -> Colour {
+        let first_hit = || {
+            stats::record(|c| c.primary += 1);
+            world.intersect(&self.ray_for_pixel(x, y)).hit()
+        };
+        match view {
+            DebugView::IntersectionTests { max } => {
+                let _counting = stats::start_counting();
+                let before = stats::snapshot();
+                self.shade_pixel(world, x, y);
+                let tests = (stats::snapshot() - before).intersection_tests();
+                heat(tests as Float / max as Float)
+            }
+            DebugView::RecursionDepth => {
+                let _counting = stats::start_counting();
+                let before = stats::snapshot();
+                self.shade_pixel(world, x, y);
+                let limit = match self.integrator {
+                    Integrator::Whitted => REF_RECURSION_LIMIT,
+                    Integrator::PathTracer { max_depth, .. } => max_depth,
+                };
+                let depth = (stats::snapshot() - before).average_depth() / limit as f64;
+                heat(depth as Float)
+            }
+            DebugView::Normals => first_hit().map_or(Colour::black(), |hit| {
+                let n = hit.normalv().normalise().0;
+                Colour::new(n[0] + 1.0, n[1] + 1.0, n[2] + 1.0) * 0.5
+            }),
+            DebugView::Uv => first_hit().map_or(Colour::black(), |hit| {
+                let (u, v) = hit.object().uv_at(hit.point());
+                Colour::new(u, v, 0.0)
+            }),
+            DebugView::Albedo => {
+                first_hit().map_or(Colour::black(), |hit| hit.object().colour_at(hit.point()))
+            }
+            DebugView::Shadows => first_hit().map_or(Colour::black(), |hit| {
+                world.shadow_transmittance(hit.over_point(), world.light.position)
+            }),
+        }
+    }
+
+    fn shade_pixel(&self, world: &World, x: usize, y: usize) -> Colour {
+        match self.integrator {
+            Integrator::Whitted => {
+                stats::record(|c| c.primary += 1);
+                world.colour_at(&self.ray_for_pixel(x, y), REF_RECURSION_LIMIT)
+            }
+            Integrator::PathTracer { samples, max_depth } => {
+                stats::record(|c| c.primary += samples as u64);
+                let mut rng = Rng::new((y * self.hsize + x) as u64);
+                let total = (0..samples).fold(Colour::black(), |acc, _| {
+                    let ray = self.ray_for_subpixel(x, y, rng.next_float(), rng.next_float());
+                    acc + world.path_colour(&ray, 0, max_depth, &mut rng)
+                });
+                total * (1.0 / samples as Float)
+            }
+        }
+    }
+
+    // The Whitted ray tree behind one pixel, whatever the integrator.
+    pub fn inspect_pixel(&self, world: &World, x: usize, y: usize) -> PathNode {
+        world.inspect(&self.ray_for_pixel(x, y), REF_RECURSION_LIMIT)
     }
 
     pub fn render(&self, world: World) -> Canvas {
+        self.render_passes(&world, false, false).0
+    }
+
+    pub fn render_with_aovs(&self, world: World) -> (Canvas, Aovs) {
+        let (image, aovs, _) = self.render_passes(&world, true, false);
+        (image, aovs.unwrap())
+    }
+
+    pub fn render_with_stats(&self, world: World) -> (Canvas, RenderStats) {
+        let (image, _, stats) = self.render_passes(&world, false, true);
+        (image, stats)
+    }
+
+    // Ray counts are only kept with `with_stats`; the timings always are.
+    fn render_passes(
+        &self,
+        world: &World,
+        with_aovs: bool,
+        with_stats: bool,
+    ) -> (Canvas, Option<Aovs>, RenderStats) {
         use indicatif::ProgressBar;
-        let progress = ProgressBar::new((self.hsize * self.vsize) as u64);
+        let start = Instant::now();
+        let progress = if self.progress {
+            ProgressBar::new((self.hsize * self.vsize) as u64)
+        } else {
+            ProgressBar::hidden()
+        };
 
         let image_mutex = Mutex::new(Canvas::new(self.hsize, self.vsize, Colour::white()));
+        let aovs_mutex = Mutex::new(with_aovs.then(|| Aovs::new(self.hsize, self.vsize)));
+        let stats_mutex = Mutex::new(RenderStats::default());
 
         (0..self.vsize)
-            .cartesian_product(0..self.hsize)
+            .step_by(TILE_SIZE)
+            .cartesian_product((0..self.hsize).step_by(TILE_SIZE))
             .par_bridge()
-            .for_each(|(y, x)| {
-                let ray = self.ray_for_pixel(x, y);
-                let colour = world.colour_at(&ray, REF_RECURSION_LIMIT);
+            .for_each(|(top, left)| {
+                let tile_start = Instant::now();
+                let _counting = with_stats.then(stats::start_counting);
+                let mut counts = stats::RayCounts::default();
+                let mut pixels = vec![];
+                let mut aov_pixels = vec![];
+                for y in top..(top + TILE_SIZE).min(self.vsize) {
+                    for x in left..(left + TILE_SIZE).min(self.hsize) {
+                        let before = stats::snapshot();
+                        pixels.push((x, y, self.colour_for_pixel(world, x, y)));
+                        counts = counts + (stats::snapshot() - before);
+
+                        if with_aovs {
+                            let ray = self.ray_for_pixel(x, y);
+                            if let Some(pixel) = AovPixel::trace(world, &ray) {
+                                aov_pixels.push((x, y, pixel));
+                            }
+                        }
+                    }
+                }
+
                 let mut image = image_mutex.lock().unwrap();
-                image.write_pixel(x, y, colour);
+                for (x, y, colour) in pixels.iter() {
+                    image.write_pixel(*x, *y, *colour);
+                }
+                drop(image);
 
-                progress.inc(1);
+                if let Some(aovs) = aovs_mutex.lock().unwrap().as_mut() {
+                    for (x, y, pixel) in aov_pixels.iter() {
+                        aovs.write(*x, *y, pixel);
+                    }
+                }
+
+                let mut stats = stats_mutex.lock().unwrap();
+                stats.counts = stats.counts + counts;
+                stats.tile_times.push(tile_start.elapsed());
+
+                progress.inc(pixels.len() as u64);
             });
 
         progress.finish();
 
-        image_mutex.into_inner().unwrap()
+        let mut stats = stats_mutex.into_inner().unwrap();
+        stats.elapsed = start.elapsed();
+        (
+            image_mutex.into_inner().unwrap(),
+            aovs_mutex.into_inner().unwrap(),
+            stats,
+        )
     }
 }
 
 #[cfg(test)]
 mod test {
-    use std::f64::consts::PI;
-
     use crate::{
-        camera::Camera,
+        camera::{Camera, DebugView, Integrator},
         colour::Colour,
         float4::Float4,
         matrix::{rotate_y, translate, view_transform, Matrix},
-        util::float_is_eq,
+        object::{Material, Object, Shape},
+        util::{consts::PI, float_is_eq, Float},
+        vector::{Point3, Vector3},
         world::World,
     };
 
@@ -112,13 +407,13 @@ mod test {
     fn ray_for_pixel() {
         let c1 = Camera::new(201, 101, PI / 2.0, Matrix::identity(4));
         let r1 = c1.ray_for_pixel(100, 50);
-        assert_eq!(r1.origin, Float4::origin());
-        assert_eq!(r1.direction, Float4::new_vector(0.0, 0.0, -1.0));
+        assert_eq!(r1.origin, Point3::origin());
+        assert_eq!(r1.direction, Vector3::new(0.0, 0.0, -1.0));
 
         let c2 = Camera::new(201, 101, PI / 2.0, Matrix::identity(4));
         let r2 = c2.ray_for_pixel(0, 0);
-        assert_eq!(r2.origin, Float4::origin());
-        assert_eq!(r2.direction, Float4::new_vector(0.66519, 0.33259, -0.66851));
+        assert_eq!(r2.origin, Point3::origin());
+        assert_eq!(r2.direction, Vector3::new(0.66519, 0.33259, -0.66851));
 
         let c3 = Camera::new(
             201,
@@ -127,10 +422,10 @@ mod test {
             rotate_y(PI / 4.0) * translate(0.0, -2.0, 5.0),
         );
         let r3 = c3.ray_for_pixel(100, 50);
-        assert_eq!(r3.origin, Float4::new_point(0.0, 2.0, -5.0));
+        assert_eq!(r3.origin, Point3::new(0.0, 2.0, -5.0));
         assert_eq!(
             r3.direction,
-            Float4::new_vector(1.0 / 2_f64.sqrt(), 0.0, -1.0 / 2_f64.sqrt())
+            Vector3::new(1.0 / Float::sqrt(2.0), 0.0, -1.0 / Float::sqrt(2.0))
         );
     }
 
@@ -146,4 +441,127 @@ mod test {
         let i = c.render(w);
         assert_eq!(i.pixels[5][5], Colour::new(0.38066, 0.47583, 0.2855));
     }
+
+    #[test]
+    fn render_with_aovs() {
+        let w = World::default();
+        let transform = view_transform(
+            Float4::new_point(0.0, 0.0, -5.0),
+            Float4::origin(),
+            Float4::new_vector(0.0, 1.0, 0.0),
+        );
+        let c = Camera::new(11, 11, PI / 2.0, transform);
+        let (image, aovs) = c.render_with_aovs(w);
+        assert_eq!(image.pixels[5][5], Colour::new(0.38066, 0.47583, 0.2855));
+        assert_eq!(aovs.depth.pixels[5][5], Colour::new(4.0, 4.0, 4.0));
+        assert_eq!(aovs.normal.pixels[5][5], Colour::new(0.5, 0.5, 0.0));
+        assert_eq!(aovs.albedo.pixels[5][5], Colour::new(0.8, 1.0, 0.6));
+        assert_eq!(aovs.object_id.pixels[5][5], Colour::new(1.0, 1.0, 1.0));
+        assert_eq!(aovs.shadow.pixels[5][5], Colour::black());
+
+        assert_eq!(aovs.depth.pixels[0][0], Colour::black());
+        assert_eq!(aovs.object_id.pixels[0][0], Colour::black());
+
+        let ldr = aovs.normalised();
+        let depth = ldr.depth.pixels[5][5].0 .0[0];
+        assert!(depth > 0.0 && depth < 1.0);
+        assert!(ldr.depth.pixels.iter().flatten().all(|c| c.0 .0[0] <= 1.0));
+        assert_eq!(ldr.object_id.pixels[5][5], Colour::white());
+        assert_eq!(ldr.normal, aovs.normal);
+    }
+
+    #[test]
+    fn render_with_stats() {
+        let w = World::default();
+        let transform = view_transform(
+            Float4::new_point(0.0, 0.0, -5.0),
+            Float4::origin(),
+            Float4::new_vector(0.0, 1.0, 0.0),
+        );
+        let c = Camera::new(20, 20, PI / 2.0, transform);
+        let (image, stats) = c.render_with_stats(w);
+        assert_eq!(image, c.render(World::default()));
+
+        let counts = stats.counts;
+        assert_eq!(counts.primary, 400);
+        // primary rays test both spheres, shadow rays stop at the first opaque one
+        assert!(counts.sphere_tests > 2 * counts.primary + counts.shadow);
+        assert!(counts.sphere_tests <= 2 * (counts.primary + counts.shadow));
+        assert_eq!(counts.plane_tests, 0);
+        assert_eq!(counts.shadow, counts.shaded);
+        assert_eq!(counts.reflection + counts.refraction + counts.truncated, 0);
+        assert_eq!(counts.average_depth(), 0.0);
+        assert_eq!(stats.tile_times.len(), 4);
+        assert!(stats.elapsed >= *stats.tile_times.iter().max().unwrap());
+
+        // bounces off matte surfaces are their own kind of ray
+        let path_traced = c.with_integrator(Integrator::PathTracer {
+            samples: 1,
+            max_depth: 2,
+        });
+        let (_, stats) = path_traced.render_with_stats(World::default());
+        assert!(stats.counts.diffuse > 0);
+        assert_eq!(stats.counts.reflection + stats.counts.environment, 0);
+    }
+
+    #[test]
+    fn render_debug_views() {
+        let transform = view_transform(
+            Float4::new_point(0.0, 0.0, -5.0),
+            Float4::origin(),
+            Float4::new_vector(0.0, 1.0, 0.0),
+        );
+        let render = |view| {
+            Camera::new(11, 11, PI / 2.0, transform.clone())
+                .with_debug_view(view)
+                .render(World::default())
+        };
+
+        let normals = render(DebugView::Normals);
+        assert_eq!(normals.pixels[5][5], Colour::new(0.5, 0.5, 0.0));
+        assert_eq!(normals.pixels[0][0], Colour::black());
+        let uv = render(DebugView::Uv);
+        assert_eq!(uv.pixels[5][5], Colour::new(0.0, 0.5, 0.0));
+        let albedo = render(DebugView::Albedo);
+        assert_eq!(albedo.pixels[5][5], Colour::new(0.8, 1.0, 0.6));
+        let shadows = render(DebugView::Shadows);
+        assert_eq!(shadows.pixels[5][5], Colour::white());
+
+        // two spheres for the primary ray, and two more for the shadow ray on a hit
+        let tests = render(DebugView::IntersectionTests { max: 4 });
+        assert_eq!(tests.pixels[5][5], Colour::white());
+        assert_eq!(tests.pixels[0][0], Colour::new(1.0, 0.5, 0.0));
+        let depth = render(DebugView::RecursionDepth);
+        assert_eq!(depth.pixels[5][5], Colour::black());
+    }
+
+    #[test]
+    fn render_path_traced() {
+        let lamp = Object {
+            shape: Shape::Sphere,
+            transform: Matrix::identity(4).into(),
+            material: Material {
+                diffuse: 0.0,
+                emissive: Colour::new(0.5, 0.25, 1.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let w = World {
+            objects: vec![lamp],
+            ..Default::default()
+        };
+        let transform = view_transform(
+            Float4::new_point(0.0, 0.0, -5.0),
+            Float4::origin(),
+            Float4::new_vector(0.0, 1.0, 0.0),
+        );
+        let c = Camera::new(11, 11, PI / 2.0, transform).with_integrator(Integrator::PathTracer {
+            samples: 4,
+            max_depth: 4,
+        });
+        let i = c.render(w);
+        assert_eq!(i.pixels[5][5], Colour::new(0.5, 0.25, 1.0));
+        assert_eq!(i.pixels[0][0], Colour::black());
+    }
 }
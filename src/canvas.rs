@@ -1,6 +1,11 @@
 use crate::colour::*;
+use crate::tonemap::ToneMap;
+use crate::util::Float;
+use std::fmt::Write;
+use std::io::{Error, ErrorKind};
 use std::path::Path;
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct Canvas {
     pub width: usize,
     pub height: usize,
@@ -20,18 +25,18 @@ impl Canvas {
         self.pixels[y][x] = colour;
     }
 
-    fn to_ppm(&self) -> String {
+    pub fn pixel_at(&self, x: usize, y: usize) -> Colour {
+        self.pixels[y][x]
+    }
+
+    pub fn to_ppm(&self) -> String {
         let mut s = format!("P3\n{} {}\n{}\n", self.width, self.height, 255);
 
         for row in self.pixels.iter() {
             for colour in row {
-                let colour = colour.0 .0;
-                let colour = colour
-                    .iter()
-                    .map(|c| (c.max(0.0).min(1.0) * 255.0).round())
-                    // .map(|c| (c * 255.0).round())
-                    .collect::<Vec<_>>();
-                s.push_str(format!("{} {} {} ", colour[0], colour[1], colour[2]).as_str());
+                let [r, g, b] = colour_to_bytes(*colour);
+                // writing to a String cannot fail
+                let _ = write!(s, "{} {} {} ", r, g, b);
             }
             s.pop();
             s.push('\n');
@@ -40,17 +45,381 @@ impl Canvas {
         s
     }
 
+    pub fn to_ppm_binary(&self) -> Vec<u8> {
+        let mut bytes = format!("P6\n{} {}\n{}\n", self.width, self.height, 255).into_bytes();
+        bytes.reserve(self.width * self.height * 3);
+        for row in self.pixels.iter() {
+            for colour in row {
+                bytes.extend(colour_to_bytes(*colour));
+            }
+        }
+        bytes
+    }
+
+    pub fn tone_mapped(&self, tone_map: &ToneMap) -> Self {
+        Self {
+            pixels: self
+                .pixels
+                .iter()
+                .map(|row| row.iter().map(|c| tone_map.apply(*c)).collect())
+                .collect(),
+            ..*self
+        }
+    }
+
+    // Flat (uncompressed) Radiance RGBE.
+    fn to_hdr(&self) -> Vec<u8> {
+        let mut bytes = format!(
+            "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+            self.height, self.width
+        )
+        .into_bytes();
+        for row in self.pixels.iter() {
+            for colour in row {
+                bytes.extend(colour_to_rgbe(*colour));
+            }
+        }
+        bytes
+    }
+
+    // Little-endian PFM; rows run from the bottom of the image up.
+    // (the cast is a no-op with the `f32` feature)
+    #[allow(clippy::unnecessary_cast)]
+    fn to_pfm(&self) -> Vec<u8> {
+        let mut bytes = format!("PF\n{} {}\n-1.0\n", self.width, self.height).into_bytes();
+        for row in self.pixels.iter().rev() {
+            for colour in row {
+                for c in &colour.0 .0[..3] {
+                    bytes.extend((*c as f32).to_le_bytes());
+                }
+            }
+        }
+        bytes
+    }
+
+    // The format follows the extension: `.hdr` and `.pfm` keep the full range,
+    // `.png` and anything else are clamped to 8 bits, the latter as plain (P3)
+    // PPM. Use `to_file_binary` for the much smaller P6.
     pub fn to_file(&self, path: &Path) -> std::io::Result<()> {
-        let a = self.to_ppm();
-        std::fs::write(path, a)
+        self.to_file_tone_mapped(path, &ToneMap::default())
+    }
+
+    // As `to_file`, but PPM is written as binary (P6).
+    pub fn to_file_binary(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.encode(path, &ToneMap::default(), true)?)
+    }
+
+    // As `to_file`, with `tone_map` applied first for the 8-bit formats only.
+    pub fn to_file_tone_mapped(&self, path: &Path, tone_map: &ToneMap) -> std::io::Result<()> {
+        std::fs::write(path, self.encode(path, tone_map, false)?)
+    }
+
+    fn encode(&self, path: &Path, tone_map: &ToneMap, binary: bool) -> std::io::Result<Vec<u8>> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("hdr") => Ok(self.to_hdr()),
+            Some("pfm") => Ok(self.to_pfm()),
+            Some("png") => self.tone_mapped(tone_map).to_png(),
+            _ if binary => Ok(self.tone_mapped(tone_map).to_ppm_binary()),
+            _ => Ok(self.tone_mapped(tone_map).to_ppm().into_bytes()),
+        }
+    }
+
+    // Radiance `.hdr`, PNG or PPM, chosen by the extension.
+    pub fn read_file(path: &Path) -> std::io::Result<Self> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("hdr") => Self::read_hdr(path),
+            Some("png") => Self::from_png(&std::fs::read(path)?),
+            _ => Self::from_ppm(&std::fs::read(path)?),
+        }
+    }
+
+    fn to_png(&self) -> std::io::Result<Vec<u8>> {
+        let mut bytes = vec![];
+        let mut encoder = png::Encoder::new(&mut bytes, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let data = self
+            .pixels
+            .iter()
+            .flatten()
+            .flat_map(|c| colour_to_bytes(*c))
+            .collect::<Vec<_>>();
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&data))
+            .map_err(Error::other)?;
+        Ok(bytes)
+    }
+
+    // Any bit depth and colour type; alpha is ignored.
+    pub fn from_png(bytes: &[u8]) -> std::io::Result<Self> {
+        let invalid = |e: png::DecodingError| Error::new(ErrorKind::InvalidData, e);
+
+        let mut decoder = png::Decoder::new(bytes);
+        // palettes become RGB and low bit depths are widened to eight
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let mut reader = decoder.read_info().map_err(invalid)?;
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data).map_err(invalid)?;
+
+        let channels = info.color_type.samples();
+        let (size, maxval) = match info.bit_depth {
+            png::BitDepth::Sixteen => (2, 65535.0),
+            _ => (1, 255.0),
+        };
+        let (width, height) = (info.width as usize, info.height as usize);
+        let mut canvas = Canvas::new(width, height, Colour::black());
+        for y in 0..height {
+            let row = &data[y * info.line_size..];
+            for x in 0..width {
+                let sample = |c: usize| {
+                    let start = (x * channels + c) * size;
+                    row[start..start + size]
+                        .iter()
+                        .fold(0, |acc, &b| acc << 8 | b as usize) as Float
+                        / maxval
+                };
+                let colour = if channels < 3 {
+                    let v = sample(0);
+                    Colour::new(v, v, v)
+                } else {
+                    Colour::new(sample(0), sample(1), sample(2))
+                };
+                canvas.write_pixel(x, y, colour);
+            }
+        }
+        Ok(canvas)
+    }
+
+    // Plain (P3) or binary (P6), with comments and any maxval up to 65535.
+    pub fn from_ppm(bytes: &[u8]) -> std::io::Result<Self> {
+        let invalid = |message: &str| Error::new(ErrorKind::InvalidData, message.to_string());
+
+        let mut header = PpmTokens { bytes, pos: 0 };
+        let binary = match header.token()? {
+            b"P3" => false,
+            b"P6" => true,
+            _ => return Err(invalid("not a P3 or P6 PPM")),
+        };
+        let (width, height, maxval) = (header.number()?, header.number()?, header.number()?);
+        if maxval == 0 || maxval > 65535 {
+            return Err(invalid("maxval out of range"));
+        }
+
+        let samples = width
+            .checked_mul(height)
+            .and_then(|n| n.checked_mul(3))
+            .ok_or_else(|| invalid("PPM dimensions too large"))?;
+        let values = if binary {
+            // a single whitespace byte separates the header from the raster
+            let raster = bytes.get(header.pos + 1..).unwrap_or_default();
+            let size = if maxval < 256 { 1 } else { 2 };
+            if raster.len() / size < samples {
+                return Err(invalid("truncated PPM"));
+            }
+            raster
+                .chunks_exact(size)
+                .take(samples)
+                .map(|c| c.iter().fold(0, |acc, &b| acc << 8 | b as usize))
+                .collect::<Vec<_>>()
+        } else {
+            (0..samples)
+                .map(|_| header.number())
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        let mut canvas = Canvas::new(width, height, Colour::black());
+        for (i, rgb) in values.chunks_exact(3).enumerate() {
+            let channel = |v: usize| v.min(maxval) as Float / maxval as Float;
+            canvas.write_pixel(
+                i % width,
+                i / width,
+                Colour::new(channel(rgb[0]), channel(rgb[1]), channel(rgb[2])),
+            );
+        }
+        Ok(canvas)
+    }
+
+    pub fn read_hdr(path: &Path) -> std::io::Result<Self> {
+        Self::from_hdr(&std::fs::read(path)?)
+    }
+
+    // Radiance RGBE, either flat or with run-length encoded scanlines. Only the
+    // usual `-Y height +X width` orientation is supported.
+    pub fn from_hdr(bytes: &[u8]) -> std::io::Result<Self> {
+        let invalid = |message: &str| Error::new(ErrorKind::InvalidData, message.to_string());
+
+        let mut rest = bytes;
+        let mut line = || {
+            let end = rest.iter().position(|&b| b == b'\n')?;
+            let line = String::from_utf8_lossy(&rest[..end]).into_owned();
+            rest = &rest[end + 1..];
+            Some(line)
+        };
+
+        let magic = line().ok_or_else(|| invalid("missing header"))?;
+        if !magic.starts_with("#?") {
+            return Err(invalid("not a Radiance HDR file"));
+        }
+        loop {
+            let header = line().ok_or_else(|| invalid("unterminated header"))?;
+            if header.is_empty() {
+                break;
+            }
+            if header.starts_with("FORMAT=") && header != "FORMAT=32-bit_rle_rgbe" {
+                return Err(invalid("unsupported pixel format"));
+            }
+        }
+        let resolution = line().ok_or_else(|| invalid("missing resolution"))?;
+        let (height, width) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+            ["-Y", h, "+X", w] => (
+                h.parse::<usize>().map_err(|_| invalid("bad height"))?,
+                w.parse::<usize>().map_err(|_| invalid("bad width"))?,
+            ),
+            _ => return Err(invalid("unsupported orientation")),
+        };
+        if width == 0 || height == 0 {
+            return Err(invalid("empty HDR image"));
+        }
+        // a run-length scanline still takes two bytes per 127 values of each channel
+        let scanline_bytes = if (8..0x8000).contains(&width) {
+            4 + 8 * width.div_ceil(127)
+        } else {
+            4 * width
+        };
+        let needed = height
+            .checked_mul(scanline_bytes)
+            .ok_or_else(|| invalid("HDR dimensions too large"))?;
+        if needed > rest.len() {
+            return Err(invalid("truncated pixel data"));
+        }
+
+        let mut canvas = Canvas::new(width, height, Colour::black());
+        let mut data = rest.iter().copied();
+        let mut next = || data.next().ok_or_else(|| invalid("truncated pixel data"));
+        let mut scanline = vec![[0u8; 4]; width];
+        for y in 0..height {
+            let first = [next()?, next()?, next()?, next()?];
+            let rle = (8..0x8000).contains(&width)
+                && first[0] == 2
+                && first[1] == 2
+                && first[2] & 0x80 == 0;
+            if rle {
+                if ((first[2] as usize) << 8 | first[3] as usize) != width {
+                    return Err(invalid("scanline width mismatch"));
+                }
+                for channel in 0..4 {
+                    let mut x = 0;
+                    while x < width {
+                        let count = next()? as usize;
+                        let (count, run) = if count > 128 {
+                            (count - 128, Some(next()?))
+                        } else {
+                            (count, None)
+                        };
+                        if count == 0 || x + count > width {
+                            return Err(invalid("bad run length"));
+                        }
+                        for pixel in &mut scanline[x..x + count] {
+                            pixel[channel] = match run {
+                                Some(value) => value,
+                                None => next()?,
+                            };
+                        }
+                        x += count;
+                    }
+                }
+            } else {
+                scanline[0] = first;
+                for pixel in scanline.iter_mut().skip(1) {
+                    *pixel = [next()?, next()?, next()?, next()?];
+                }
+            }
+
+            for (x, [r, g, b, e]) in scanline.iter().enumerate() {
+                canvas.write_pixel(x, y, rgbe_to_colour(*r, *g, *b, *e));
+            }
+        }
+
+        Ok(canvas)
+    }
+}
+
+struct PpmTokens<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> PpmTokens<'a> {
+    // skips whitespace and comments
+    fn token(&mut self) -> std::io::Result<&'a [u8]> {
+        loop {
+            match self.bytes.get(self.pos) {
+                Some(b'#') => {
+                    while self.bytes.get(self.pos).is_some_and(|&b| b != b'\n') {
+                        self.pos += 1;
+                    }
+                }
+                Some(b) if b.is_ascii_whitespace() => self.pos += 1,
+                Some(_) => break,
+                None => return Err(Error::new(ErrorKind::InvalidData, "truncated PPM")),
+            }
+        }
+        let start = self.pos;
+        while self
+            .bytes
+            .get(self.pos)
+            .is_some_and(|b| !b.is_ascii_whitespace() && *b != b'#')
+        {
+            self.pos += 1;
+        }
+        Ok(&self.bytes[start..self.pos])
+    }
+
+    fn number(&mut self) -> std::io::Result<usize> {
+        std::str::from_utf8(self.token()?)
+            .ok()
+            .and_then(|t| t.parse().ok())
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "expected a number"))
     }
 }
 
+fn colour_to_bytes(colour: Colour) -> [u8; 3] {
+    let [r, g, b, _] = colour.0.to_array();
+    let byte = |c: Float| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    [byte(r), byte(g), byte(b)]
+}
+
+// NaN is written as zero, and anything past the largest exponent RGBE has,
+// including infinity, as the brightest value it can hold.
+fn colour_to_rgbe(colour: Colour) -> [u8; 4] {
+    let [r, g, b, _] = colour.0.to_array();
+    let max = r.max(g).max(b);
+    if max.is_nan() || max < 1e-32 {
+        return [0; 4];
+    }
+    // max = mantissa * 2^exponent with the mantissa in [0.5, 1)
+    let exponent = (max.log2().floor() as i32).min(126) + 1;
+    let scale = 256.0 / Float::powi(2.0, exponent);
+    let byte = |c: Float| (c.max(0.0) * scale).min(255.0) as u8;
+    [byte(r), byte(g), byte(b), (exponent + 128) as u8]
+}
+
+fn rgbe_to_colour(r: u8, g: u8, b: u8, e: u8) -> Colour {
+    if e == 0 {
+        return Colour::black();
+    }
+    let f = Float::powi(2.0, e as i32 - (128 + 8));
+    Colour::new(r as Float * f, g as Float * f, b as Float * f)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::tonemap::Curve;
 
     #[test]
+    #[allow(clippy::excessive_precision)]
     fn to_ppm_test() {
         let canvas = Canvas {
             width: 1,
@@ -70,4 +439,180 @@ mod test {
         };
         assert_eq!(canvas.to_ppm(), "P3\n1 1\n255\n20 7 24\n");
     }
+
+    #[test]
+    fn to_ppm_binary() {
+        let mut canvas = Canvas::new(2, 1, Colour::black());
+        canvas.write_pixel(0, 0, Colour::new(1.5, 0.5, -0.5));
+        let bytes = canvas.to_ppm_binary();
+        assert_eq!(bytes, b"P6\n2 1\n255\n\xff\x80\x00\x00\x00\x00");
+    }
+
+    #[test]
+    fn from_ppm() {
+        let plain = b"P3\n# a comment\n2 1 # trailing\n4\n4 2 0\n0 0 1\n";
+        let canvas = Canvas::from_ppm(plain).unwrap();
+        assert_eq!(canvas.width, 2);
+        assert_eq!(canvas.height, 1);
+        assert_eq!(canvas.pixel_at(0, 0), Colour::new(1.0, 0.5, 0.0));
+        assert_eq!(canvas.pixel_at(1, 0), Colour::new(0.0, 0.0, 0.25));
+
+        let mut wide = b"P6 1 1 1000\n".to_vec();
+        wide.extend([0x03, 0xe8, 0x01, 0xf4, 0x00, 0x00]);
+        let canvas = Canvas::from_ppm(&wide).unwrap();
+        assert_eq!(canvas.pixel_at(0, 0), Colour::new(1.0, 0.5, 0.0));
+
+        let mut canvas = Canvas::new(3, 2, Colour::new(0.2, 0.4, 0.6));
+        canvas.write_pixel(2, 1, Colour::white());
+        let binary = Canvas::from_ppm(&canvas.to_ppm_binary()).unwrap();
+        let plain = Canvas::from_ppm(canvas.to_ppm().as_bytes()).unwrap();
+        assert_eq!(binary, plain);
+        assert_eq!(binary.pixel_at(2, 1), Colour::white());
+
+        assert!(Canvas::from_ppm(b"P5\n1 1\n255\n\x00").is_err());
+        assert!(Canvas::from_ppm(b"P6\n2 2\n255\n\x00\x00").is_err());
+        assert!(Canvas::from_ppm(b"P3\n1 1\n255\n0 0").is_err());
+        let huge = format!("P6\n{} {}\n255\n\x00", usize::MAX / 2, 3);
+        assert!(Canvas::from_ppm(huge.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let mut canvas = Canvas::new(3, 2, Colour::new(0.2, 0.4, 0.6));
+        canvas.write_pixel(1, 1, Colour::new(2.0, -1.0, 0.5));
+        let read = Canvas::from_png(&canvas.to_png().unwrap()).unwrap();
+        assert_eq!(read, Canvas::from_ppm(&canvas.to_ppm_binary()).unwrap());
+        assert_eq!(read.pixel_at(1, 1), Colour::new(1.0, 0.0, 128.0 / 255.0));
+
+        let mut grey = vec![];
+        let mut encoder = png::Encoder::new(&mut grey, 2, 1);
+        encoder.set_color(png::ColorType::GrayscaleAlpha);
+        encoder.set_depth(png::BitDepth::Sixteen);
+        let mut writer = encoder.write_header().unwrap();
+        writer
+            .write_image_data(&[0xff, 0xff, 0, 0, 0x80, 0x00, 0xff, 0xff])
+            .unwrap();
+        writer.finish().unwrap();
+        let read = Canvas::from_png(&grey).unwrap();
+        assert_eq!(read.pixel_at(0, 0), Colour::white());
+        assert_eq!(
+            read.pixel_at(1, 0),
+            Colour::new(32768.0 / 65535.0, 32768.0 / 65535.0, 32768.0 / 65535.0)
+        );
+
+        assert!(Canvas::from_png(b"P3\n1 1\n255\n0 0 0\n").is_err());
+    }
+
+    #[test]
+    fn tone_mapped() {
+        let canvas = Canvas::new(2, 1, Colour::new(4.0, 1.0, 0.0));
+        let mapped = canvas.tone_mapped(&ToneMap {
+            curve: Curve::Reinhard,
+            ..Default::default()
+        });
+        assert_eq!(mapped.pixel_at(1, 0), Colour::new(0.8, 0.5, 0.0));
+        assert_eq!(mapped.to_ppm(), "P3\n2 1\n255\n204 128 0 204 128 0\n");
+    }
+
+    #[test]
+    fn to_hdr_round_trip() {
+        let mut canvas = Canvas::new(3, 2, Colour::black());
+        canvas.write_pixel(0, 0, Colour::new(1.0, 0.5, 0.0));
+        canvas.write_pixel(2, 1, Colour::new(12.0, 3.0, 0.75));
+        let read = Canvas::from_hdr(&canvas.to_hdr()).unwrap();
+        assert_eq!(read.pixel_at(0, 0), Colour::new(1.0, 0.5, 0.0));
+        assert_eq!(read.pixel_at(2, 1), Colour::new(12.0, 3.0, 0.75));
+        assert_eq!(read.pixel_at(1, 1), Colour::black());
+    }
+
+    #[test]
+    fn to_hdr_out_of_range() {
+        let infinite = colour_to_rgbe(Colour::new(Float::INFINITY, 1.0, Float::NAN));
+        assert_eq!(infinite, [255, 0, 0, 255]);
+        assert_eq!(colour_to_rgbe(Colour::new(Float::MAX, 0.0, 0.0))[3], 255);
+        assert_eq!(
+            colour_to_rgbe(Colour::new(Float::NAN, Float::NAN, Float::NAN)),
+            [0; 4]
+        );
+    }
+
+    #[test]
+    fn encode_tone_mapped() {
+        let canvas = Canvas::new(1, 1, Colour::new(4.0, 1.0, 0.0));
+        let reinhard = ToneMap {
+            curve: Curve::Reinhard,
+            ..Default::default()
+        };
+        let ppm = canvas
+            .encode(Path::new("out.ppm"), &reinhard, false)
+            .unwrap();
+        assert_eq!(ppm, canvas.tone_mapped(&reinhard).to_ppm().into_bytes());
+        let binary = canvas
+            .encode(Path::new("out.ppm"), &reinhard, true)
+            .unwrap();
+        assert_eq!(binary, canvas.tone_mapped(&reinhard).to_ppm_binary());
+        let png = canvas
+            .encode(Path::new("out.png"), &reinhard, true)
+            .unwrap();
+        assert_eq!(
+            Canvas::from_png(&png).unwrap().pixel_at(0, 0),
+            Colour::new(204.0 / 255.0, 128.0 / 255.0, 0.0)
+        );
+        // the full range is kept where the format can hold it
+        let hdr = canvas
+            .encode(Path::new("out.hdr"), &reinhard, false)
+            .unwrap();
+        assert_eq!(hdr, canvas.to_hdr());
+    }
+
+    #[test]
+    fn to_pfm() {
+        let mut canvas = Canvas::new(1, 2, Colour::black());
+        canvas.write_pixel(0, 1, Colour::new(2.5, 0.0, 1.0));
+        let bytes = canvas.to_pfm();
+        let header = b"PF\n1 2\n-1.0\n";
+        assert_eq!(&bytes[..header.len()], header);
+        assert_eq!(bytes.len(), header.len() + 2 * 3 * 4);
+        // the bottom row comes first
+        assert_eq!(&bytes[header.len()..header.len() + 4], 2.5f32.to_le_bytes());
+    }
+
+    #[test]
+    fn from_hdr_flat() {
+        let mut bytes = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 2\n".to_vec();
+        bytes.extend([128, 64, 0, 129, 0, 0, 0, 0]);
+        let canvas = Canvas::from_hdr(&bytes).unwrap();
+        assert_eq!(canvas.width, 2);
+        assert_eq!(canvas.height, 1);
+        assert_eq!(canvas.pixel_at(0, 0), Colour::new(1.0, 0.5, 0.0));
+        assert_eq!(canvas.pixel_at(1, 0), Colour::black());
+    }
+
+    #[test]
+    fn from_hdr_rle() {
+        let mut bytes = b"#?RGBE\n\n-Y 1 +X 8\n".to_vec();
+        bytes.extend([2, 2, 0, 8]);
+        // red: a run of eight; green: eight literals; blue: two runs; exponent: one run
+        bytes.extend([136, 128]);
+        bytes.extend([8, 0, 32, 64, 96, 128, 160, 192, 224]);
+        bytes.extend([132, 0, 132, 128]);
+        bytes.extend([136, 130]);
+        let canvas = Canvas::from_hdr(&bytes).unwrap();
+        assert_eq!(canvas.pixel_at(0, 0), Colour::new(2.0, 0.0, 0.0));
+        assert_eq!(canvas.pixel_at(7, 0), Colour::new(2.0, 3.5, 2.0));
+
+        assert!(Canvas::from_hdr(b"P3\n1 1\n255\n").is_err());
+        assert!(Canvas::from_hdr(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn from_hdr_bad_size() {
+        for resolution in ["-Y 1 +X 0", "-Y 0 +X 1", "-Y 100000 +X 100000"] {
+            let mut bytes = format!("#?RADIANCE\n\n{resolution}\n").into_bytes();
+            bytes.extend([128, 64, 0, 129]);
+            assert!(Canvas::from_hdr(&bytes).is_err());
+        }
+        let huge = format!("#?RADIANCE\n\n-Y {} +X 2\n", usize::MAX);
+        assert!(Canvas::from_hdr(huge.as_bytes()).is_err());
+    }
 }
@@ -1,11 +1,12 @@
 use crate::float4::*;
+use crate::util::Float;
 use std::ops::{Add, Mul, Sub};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Colour(pub Float4);
 
 impl Colour {
-    pub const fn new(r: f64, g: f64, b: f64) -> Self {
+    pub const fn new(r: Float, g: Float, b: Float) -> Self {
         Self(Float4::new_vector(r, g, b))
     }
 
@@ -17,7 +18,7 @@ impl Colour {
         Self::new(1.0, 1.0, 1.0)
     }
 
-    pub fn scalar_product(self, f: f64) -> Self {
+    pub fn scalar_product(self, f: Float) -> Self {
         self.0.scalar_mul(f).into()
     }
 
@@ -25,7 +26,7 @@ impl Colour {
         Self::new(
             self.0 .0[0] * rhs.0 .0[0],
             self.0 .0[1] * rhs.0 .0[1],
-            self.0 .0[2] * rhs.0 .0[1],
+            self.0 .0[2] * rhs.0 .0[2],
         )
     }
 }
@@ -60,10 +61,26 @@ impl Mul<Colour> for Colour {
     }
 }
 
-impl Mul<f64> for Colour {
+impl Mul<Float> for Colour {
     type Output = Self;
 
-    fn mul(self, rhs: f64) -> Self::Output {
+    fn mul(self, rhs: Float) -> Self::Output {
         self.scalar_product(rhs)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn multiply_colours() {
+        let product = Colour::new(1.0, 0.2, 0.4) * Colour::new(0.9, 1.0, 0.1);
+        assert_eq!(product, Colour::new(0.9, 0.2, 0.04));
+        // each channel only sees its own, blue included
+        assert_eq!(
+            Colour::new(0.0, 0.0, 1.0) * Colour::new(0.0, 1.0, 0.5),
+            Colour::new(0.0, 0.0, 0.5)
+        );
+    }
+}
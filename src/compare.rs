@@ -0,0 +1,219 @@
+use crate::{canvas::Canvas, colour::Colour, util::Float};
+
+// side of the square windows SSIM is averaged over
+const SSIM_WINDOW: usize = 8;
+// stabilising constants for SSIM with a dynamic range of one
+const SSIM_C1: Float = 0.01 * 0.01;
+const SSIM_C2: Float = 0.03 * 0.03;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Comparison {
+    // mean squared error over every channel of every pixel
+    pub mse: Float,
+    // in decibels, taking one as the peak value; infinite for identical images
+    pub psnr: Float,
+    pub max_error: Float,
+    // structural similarity of the luminance, one for identical images
+    pub ssim: Float,
+}
+
+impl Canvas {
+    // Panics if the two canvases are not the same size.
+    pub fn compare(&self, other: &Canvas) -> Comparison {
+        self.assert_same_size(other);
+
+        let mut squared = 0.0;
+        let mut max_error: Float = 0.0;
+        for (a, b) in self.colours().zip(other.colours()) {
+            for i in 0..3 {
+                let error = (a.0 .0[i] - b.0 .0[i]).abs();
+                squared += error * error;
+                max_error = max_error.max(error);
+            }
+        }
+        let mse = squared / (self.width * self.height * 3) as Float;
+
+        Comparison {
+            mse,
+            psnr: -10.0 * mse.log10(),
+            max_error,
+            ssim: ssim(
+                &self.luminance(),
+                &other.luminance(),
+                self.width,
+                self.height,
+            ),
+        }
+    }
+
+    // Per-pixel largest channel error on a black-red-yellow-white ramp. Errors of
+    // `scale` or more are white.
+    pub fn heatmap(&self, other: &Canvas, scale: Float) -> Canvas {
+        self.assert_same_size(other);
+
+        let mut heatmap = Canvas::new(self.width, self.height, Colour::black());
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (a, b) = (self.pixel_at(x, y).0 .0, other.pixel_at(x, y).0 .0);
+                let error = (0..3).map(|i| (a[i] - b[i]).abs()).fold(0.0, Float::max);
+                let t = if scale > 0.0 { error / scale } else { 0.0 };
+                heatmap.write_pixel(x, y, heat(t));
+            }
+        }
+        heatmap
+    }
+
+    fn assert_same_size(&self, other: &Canvas) {
+        assert_eq!(
+            (self.width, self.height),
+            (other.width, other.height),
+            "cannot compare canvases of different sizes"
+        );
+    }
+
+    fn colours(&self) -> impl Iterator<Item = &Colour> {
+        self.pixels.iter().flatten()
+    }
+
+    // Rec. 709 weights, clamped to the displayable range
+    fn luminance(&self) -> Vec<Float> {
+        self.colours()
+            .map(|c| {
+                let c = c.0 .0;
+                (0.2126 * c[0] + 0.7152 * c[1] + 0.0722 * c[2]).clamp(0.0, 1.0)
+            })
+            .collect()
+    }
+}
+
+// Black at zero through red and yellow to white at one and above.
+pub fn heat(t: Float) -> Colour {
+    Colour::new(
+        (3.0 * t).clamp(0.0, 1.0),
+        (3.0 * t - 1.0).clamp(0.0, 1.0),
+        (3.0 * t - 2.0).clamp(0.0, 1.0),
+    )
+}
+
+// Mean SSIM over every window position; images smaller than a window are
+// treated as a single window.
+fn ssim(a: &[Float], b: &[Float], width: usize, height: usize) -> Float {
+    let (w, h) = (SSIM_WINDOW.min(width), SSIM_WINDOW.min(height));
+    let mut total = 0.0;
+    let mut windows = 0;
+    for top in 0..=height - h {
+        for left in 0..=width - w {
+            let index = |x: usize, y: usize| (top + y) * width + left + x;
+            let n = (w * h) as Float;
+            let (mut mean_a, mut mean_b) = (0.0, 0.0);
+            for y in 0..h {
+                for x in 0..w {
+                    mean_a += a[index(x, y)];
+                    mean_b += b[index(x, y)];
+                }
+            }
+            mean_a /= n;
+            mean_b /= n;
+
+            let (mut var_a, mut var_b, mut covariance) = (0.0, 0.0, 0.0);
+            for y in 0..h {
+                for x in 0..w {
+                    let (da, db) = (a[index(x, y)] - mean_a, b[index(x, y)] - mean_b);
+                    var_a += da * da;
+                    var_b += db * db;
+                    covariance += da * db;
+                }
+            }
+            var_a /= n;
+            var_b /= n;
+            covariance /= n;
+
+            total += ((2.0 * mean_a * mean_b + SSIM_C1) * (2.0 * covariance + SSIM_C2))
+                / ((mean_a * mean_a + mean_b * mean_b + SSIM_C1) * (var_a + var_b + SSIM_C2));
+            windows += 1;
+        }
+    }
+    total / windows as Float
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::float_is_eq;
+
+    fn checkerboard(size: usize) -> Canvas {
+        let mut canvas = Canvas::new(size, size, Colour::black());
+        for y in 0..size {
+            for x in 0..size {
+                if (x + y) % 2 == 0 {
+                    canvas.write_pixel(x, y, Colour::white());
+                }
+            }
+        }
+        canvas
+    }
+
+    #[test]
+    fn identical() {
+        let image = checkerboard(10);
+        let result = image.compare(&image);
+        assert_eq!(result.mse, 0.0);
+        assert!(result.psnr.is_infinite());
+        assert_eq!(result.max_error, 0.0);
+        assert!(float_is_eq(result.ssim, 1.0));
+    }
+
+    #[test]
+    fn errors() {
+        let grey = Canvas::new(2, 2, Colour::new(0.5, 0.5, 0.5));
+        let mut off = grey.clone();
+        off.write_pixel(1, 1, Colour::new(0.5, 0.5, 0.7));
+        let result = off.compare(&grey);
+        assert!(float_is_eq(result.mse, 0.04 / 12.0));
+        assert!(float_is_eq(result.psnr, 24.77121));
+        assert!(float_is_eq(result.max_error, 0.2));
+        assert!(result.ssim < 1.0);
+    }
+
+    #[test]
+    fn ssim_prefers_structure() {
+        let mut image = Canvas::new(12, 12, Colour::black());
+        let mut shifted = image.clone();
+        let mut striped = image.clone();
+        for y in 0..12 {
+            for x in 0..12 {
+                let v = if (x + y) % 2 == 0 { 0.7 } else { 0.3 };
+                let stripe = if y % 2 == 0 { 0.1 } else { -0.1 };
+                image.write_pixel(x, y, Colour::new(v, v, v));
+                shifted.write_pixel(x, y, Colour::new(v, v, v) + Colour::new(0.1, 0.1, 0.1));
+                striped.write_pixel(
+                    x,
+                    y,
+                    Colour::new(v, v, v) + Colour::new(stripe, stripe, stripe),
+                );
+            }
+        }
+        // the same error, once as a brightness change and once as new structure
+        let (a, b) = (image.compare(&shifted), image.compare(&striped));
+        assert!(float_is_eq(a.mse, b.mse));
+        assert!(a.ssim > b.ssim);
+    }
+
+    #[test]
+    fn heatmap() {
+        let black = Canvas::new(3, 1, Colour::black());
+        let mut other = black.clone();
+        other.write_pixel(1, 0, Colour::new(0.0, 0.25, 0.0));
+        other.write_pixel(2, 0, Colour::new(0.0, 0.0, 2.0));
+        let heatmap = black.heatmap(&other, 0.5);
+        assert_eq!(heatmap.pixel_at(0, 0), Colour::black());
+        assert_eq!(heatmap.pixel_at(1, 0), Colour::new(1.0, 0.5, 0.0));
+        assert_eq!(heatmap.pixel_at(2, 0), Colour::white());
+    }
+
+    #[test]
+    #[should_panic]
+    fn different_sizes() {
+        Canvas::new(2, 2, Colour::black()).compare(&Canvas::new(2, 3, Colour::black()));
+    }
+}
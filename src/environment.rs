@@ -0,0 +1,99 @@
+use std::sync::Arc;
+
+use crate::{
+    canvas::Canvas,
+    colour::Colour,
+    float4::Float4,
+    util::{consts::PI, Float},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Background {
+    Solid(Colour),
+    // blends from `horizon` straight ahead to `zenith` straight up; below the
+    // horizon stays at `horizon`
+    Gradient { horizon: Colour, zenith: Colour },
+    // equirectangular, with +z at the centre of the image and +y along the top edge
+    Image(Arc<Canvas>),
+}
+
+impl Background {
+    pub fn at(&self, direction: Float4) -> Colour {
+        let direction = direction.normalise();
+        match self {
+            Background::Solid(colour) => *colour,
+            Background::Gradient { horizon, zenith } => {
+                let t = direction.0[1].max(0.0);
+                *horizon * (1.0 - t) + *zenith * t
+            }
+            Background::Image(image) if image.width == 0 || image.height == 0 => Colour::black(),
+            Background::Image(image) => {
+                let (x, y, z) = (direction.0[0], direction.0[1], direction.0[2]);
+                let u = 0.5 + x.atan2(z) / (2.0 * PI);
+                let v = y.clamp(-1.0, 1.0).acos() / PI;
+                let px = (u * (image.width - 1) as Float).round() as usize;
+                let py = (v * (image.height - 1) as Float).round() as usize;
+                image.pixel_at(px, py)
+            }
+        }
+    }
+}
+
+// What rays see when they leave the scene.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Environment {
+    pub background: Background,
+    // hemisphere samples taken to light diffuse surfaces; zero keeps it a backdrop
+    pub samples: usize,
+}
+
+impl Environment {
+    pub fn at(&self, direction: Float4) -> Colour {
+        self.background.at(direction)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn gradient() {
+        let sky = Background::Gradient {
+            horizon: Colour::white(),
+            zenith: Colour::new(0.0, 0.0, 1.0),
+        };
+        assert_eq!(sky.at(Float4::new_vector(0.0, 0.0, 1.0)), Colour::white());
+        assert_eq!(
+            sky.at(Float4::new_vector(0.0, 2.0, 0.0)),
+            Colour::new(0.0, 0.0, 1.0)
+        );
+        assert_eq!(sky.at(Float4::new_vector(0.0, -1.0, 0.0)), Colour::white());
+    }
+
+    #[test]
+    fn equirectangular() {
+        let mut image = Canvas::new(5, 3, Colour::black());
+        image.write_pixel(2, 1, Colour::new(1.0, 0.0, 0.0));
+        image.write_pixel(3, 1, Colour::new(0.0, 1.0, 0.0));
+        image.write_pixel(2, 0, Colour::new(0.0, 0.0, 1.0));
+        let sky = Background::Image(Arc::new(image));
+
+        assert_eq!(
+            sky.at(Float4::new_vector(0.0, 0.0, 1.0)),
+            Colour::new(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            sky.at(Float4::new_vector(1.0, 0.0, 0.0)),
+            Colour::new(0.0, 1.0, 0.0)
+        );
+        assert_eq!(
+            sky.at(Float4::new_vector(0.0, 1.0, 0.0)),
+            Colour::new(0.0, 0.0, 1.0)
+        );
+
+        // an empty image shows nothing rather than indexing outside itself
+        let empty = Background::Image(Arc::new(Canvas::new(0, 0, Colour::white())));
+        assert_eq!(empty.at(Float4::new_vector(0.0, 0.0, 1.0)), Colour::black());
+    }
+}
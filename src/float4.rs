@@ -1,25 +1,60 @@
 use std::ops::{Add, Neg, Sub};
+#[cfg(feature = "simd")]
+use std::simd::{num::SimdFloat, simd_swizzle, Simd};
 
-use crate::{matrix::Matrix, util::float_is_eq};
+use crate::{
+    matrix::Matrix,
+    util::{float_is_eq, Float},
+};
+
+// With the `simd` feature a Float4 is kept in four lanes, and the arithmetic
+// below works on them directly. `.0[i]` indexes either kind of storage.
+
+#[cfg(feature = "simd")]
+type Lanes = Simd<Float, 4>;
+#[cfg(feature = "simd")]
+type Storage = Lanes;
+#[cfg(not(feature = "simd"))]
+type Storage = [Float; 4];
 
 #[derive(Debug, Clone, Copy)]
-pub struct Float4(pub [f64; 4]);
+pub struct Float4(pub Storage);
 
 impl Float4 {
-    pub fn new(x: f64, y: f64, z: f64, w: f64) -> Self {
-        Self([x, y, z, w])
+    pub const fn new(x: Float, y: Float, z: Float, w: Float) -> Self {
+        Self::from_array([x, y, z, w])
     }
 
-    pub fn new_point(x: f64, y: f64, z: f64) -> Self {
-        Self([x, y, z, 1.0])
+    #[cfg(feature = "simd")]
+    pub const fn from_array(array: [Float; 4]) -> Self {
+        Self(Lanes::from_array(array))
+    }
+
+    #[cfg(not(feature = "simd"))]
+    pub const fn from_array(array: [Float; 4]) -> Self {
+        Self(array)
+    }
+
+    #[cfg(feature = "simd")]
+    pub fn to_array(self) -> [Float; 4] {
+        self.0.to_array()
+    }
+
+    #[cfg(not(feature = "simd"))]
+    pub fn to_array(self) -> [Float; 4] {
+        self.0
+    }
+
+    pub fn new_point(x: Float, y: Float, z: Float) -> Self {
+        Self::new(x, y, z, 1.0)
     }
 
     pub fn origin() -> Self {
         Self::new_point(0.0, 0.0, 0.0)
     }
 
-    pub const fn new_vector(x: f64, y: f64, z: f64) -> Self {
-        Self([x, y, z, 0.0])
+    pub const fn new_vector(x: Float, y: Float, z: Float) -> Self {
+        Self::new(x, y, z, 0.0)
     }
 
     pub fn is_point(self) -> bool {
@@ -30,7 +65,13 @@ impl Float4 {
         float_is_eq(self.0[3], 0.0)
     }
 
-    pub fn scalar_mul(self, rhs: f64) -> Self {
+    #[cfg(feature = "simd")]
+    pub fn scalar_mul(self, rhs: Float) -> Self {
+        Self(self.0 * Lanes::splat(rhs))
+    }
+
+    #[cfg(not(feature = "simd"))]
+    pub fn scalar_mul(self, rhs: Float) -> Self {
         Self::new(
             self.0[0] * rhs,
             self.0[1] * rhs,
@@ -39,11 +80,17 @@ impl Float4 {
         )
     }
 
-    // pub fn scalar_div(self, rhs: f64) -> Self {
+    // pub fn scalar_div(self, rhs: Float) -> Self {
     //     self.scalar_mul(-1.0 / rhs) // @TODO
     // }
 
-    pub fn mag(&self) -> f64 {
+    #[cfg(feature = "simd")]
+    pub fn mag(&self) -> Float {
+        self.dot(*self).sqrt()
+    }
+
+    #[cfg(not(feature = "simd"))]
+    pub fn mag(&self) -> Float {
         (self.0[0] * self.0[0]
             + self.0[1] * self.0[1]
             + self.0[2] * self.0[2]
@@ -51,6 +98,12 @@ impl Float4 {
             .sqrt()
     }
 
+    #[cfg(feature = "simd")]
+    pub fn normalise(self) -> Self {
+        Self(self.0 / Lanes::splat(self.mag()))
+    }
+
+    #[cfg(not(feature = "simd"))]
     pub fn normalise(self) -> Self {
         let mag = self.mag();
         Self::new(
@@ -61,10 +114,28 @@ impl Float4 {
         )
     }
 
-    pub fn dot(self, rhs: Self) -> f64 {
+    #[cfg(feature = "simd")]
+    pub fn dot(self, rhs: Self) -> Float {
+        (self.0 * rhs.0).reduce_sum()
+    }
+
+    #[cfg(not(feature = "simd"))]
+    pub fn dot(self, rhs: Self) -> Float {
         self.0[0] * rhs.0[0] + self.0[1] * rhs.0[1] + self.0[2] * rhs.0[2] + self.0[3] * rhs.0[3]
     }
 
+    // a.yzx * b.zxy - a.zxy * b.yzx, with w coming out as zero
+    #[cfg(feature = "simd")]
+    pub fn cross(self, rhs: Self) -> Self {
+        let (a, b) = (self.0, rhs.0);
+        let a_yzx = simd_swizzle!(a, [1, 2, 0, 3]);
+        let a_zxy = simd_swizzle!(a, [2, 0, 1, 3]);
+        let b_yzx = simd_swizzle!(b, [1, 2, 0, 3]);
+        let b_zxy = simd_swizzle!(b, [2, 0, 1, 3]);
+        Self(a_yzx * b_zxy - a_zxy * b_yzx)
+    }
+
+    #[cfg(not(feature = "simd"))]
     pub fn cross(self, rhs: Self) -> Self {
         Self::new_vector(
             self.0[1] * rhs.0[2] - self.0[2] * rhs.0[1],
@@ -83,6 +154,12 @@ impl Float4 {
 impl Add for Float4 {
     type Output = Self;
 
+    #[cfg(feature = "simd")]
+    fn add(self, rhs: Self) -> Self::Output {
+        Self(self.0 + rhs.0)
+    }
+
+    #[cfg(not(feature = "simd"))]
     fn add(self, rhs: Self) -> Self::Output {
         Self::new(
             self.0[0] + rhs.0[0],
@@ -96,6 +173,12 @@ impl Add for Float4 {
 impl Sub for Float4 {
     type Output = Self;
 
+    #[cfg(feature = "simd")]
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self(self.0 - rhs.0)
+    }
+
+    #[cfg(not(feature = "simd"))]
     fn sub(self, rhs: Self) -> Self::Output {
         self.add(rhs.neg())
     }
@@ -104,6 +187,12 @@ impl Sub for Float4 {
 impl Neg for Float4 {
     type Output = Self;
 
+    #[cfg(feature = "simd")]
+    fn neg(self) -> Self::Output {
+        Self(-self.0)
+    }
+
+    #[cfg(not(feature = "simd"))]
     fn neg(self) -> Self::Output {
         self.scalar_mul(-1.0)
     }
@@ -121,7 +210,7 @@ impl PartialEq for Float4 {
 impl From<Matrix> for Float4 {
     fn from(value: Matrix) -> Self {
         assert!(value.0.len() == 4 && value.0[0].len() == 1);
-        Self([value.0[0][0], value.0[1][0], value.0[2][0], value.0[3][0]])
+        Self::new(value.0[0][0], value.0[1][0], value.0[2][0], value.0[3][0])
     }
 }
 
@@ -136,7 +225,24 @@ mod test {
         assert_eq!(v.reflect(n), Float4::new_vector(1.0, 1.0, 0.0));
 
         let v = Float4::new_vector(0.0, -1.0, 0.0);
-        let n = Float4::new_vector(1.0 / 2.0_f64.sqrt(), 1.0 / 2.0_f64.sqrt(), 0.0);
+        let n = Float4::new_vector(1.0 / Float::sqrt(2.0), 1.0 / Float::sqrt(2.0), 0.0);
         assert_eq!(v.reflect(n), Float4::new_vector(1.0, 0.0, 0.0));
     }
+
+    // run with and without the `simd` feature; both must agree with these
+    #[test]
+    fn arithmetic() {
+        let a = Float4::new_vector(1.0, -2.0, 3.0);
+        let b = Float4::new_vector(2.0, 3.0, 4.0);
+        let p = Float4::new_point(1.0, 2.0, 3.0);
+        assert_eq!(a + p, Float4::new_point(2.0, 0.0, 6.0));
+        assert_eq!(p - a, Float4::new_point(0.0, 4.0, 0.0));
+        assert_eq!(-a, Float4::new_vector(-1.0, 2.0, -3.0));
+        assert_eq!(a.scalar_mul(0.5), Float4::new_vector(0.5, -1.0, 1.5));
+        assert!(float_is_eq(a.dot(b), 8.0));
+        assert!(float_is_eq(a.mag(), Float::sqrt(14.0)));
+        assert!(float_is_eq(a.normalise().mag(), 1.0));
+        assert_eq!(a.cross(b), Float4::new_vector(-17.0, 2.0, 7.0));
+        assert_eq!(b.cross(a), Float4::new_vector(17.0, -2.0, -7.0));
+    }
 }
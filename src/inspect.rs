@@ -0,0 +1,356 @@
+use std::fmt::{self, Write};
+
+use crate::{
+    colour::Colour,
+    float4::Float4,
+    ray::{Intersection, Ray},
+    util::Float,
+    vector::{Point3, Vector3},
+    world::{beer_lambert, World},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RayKind {
+    Primary,
+    Reflection,
+    Refraction,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HitRecord {
+    // index into `World::objects`
+    pub object: Option<usize>,
+    pub distance: Float,
+    pub point: Float4,
+    pub normal: Float4,
+    pub n1: Float,
+    pub n2: Float,
+    pub schlick: Float,
+    // fraction of the point light that reaches the hit
+    pub shadow: Colour,
+}
+
+// One ray of a Whitted ray tree. Rough surfaces are followed along the centre
+// of their lobe, though `colour` and `contribution` include the whole lobe.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathNode {
+    pub kind: RayKind,
+    pub ray: Ray,
+    pub hit: Option<HitRecord>,
+    // everything seen along this ray
+    pub colour: Colour,
+    // how much of `colour` reaches the parent after reflectance and transparency
+    pub contribution: Colour,
+    pub children: Vec<PathNode>,
+}
+
+impl World {
+    pub fn inspect(&self, ray: &Ray, remaining: u8) -> PathNode {
+        let mut node = self.inspect_ray(ray, RayKind::Primary, remaining);
+        node.contribution = node.colour;
+        node
+    }
+
+    fn inspect_ray(&self, ray: &Ray, kind: RayKind, remaining: u8) -> PathNode {
+        let colour = self.colour_at(ray, remaining);
+        let mut node = PathNode {
+            kind,
+            ray: *ray,
+            hit: None,
+            colour,
+            contribution: Colour::black(),
+            children: vec![],
+        };
+        let Some(hit) = self.intersect(ray).hit() else {
+            return node;
+        };
+
+        node.hit = Some(HitRecord {
+            object: self
+                .objects
+                .iter()
+                .position(|o| std::ptr::eq(o, hit.object())),
+            distance: hit.distance(),
+            point: hit.point(),
+            normal: hit.normalv(),
+            n1: hit.n1(),
+            n2: hit.n2(),
+            schlick: hit.schlick(),
+            shadow: self.shadow_transmittance(hit.over_point(), self.light.position),
+        });
+        if remaining == 0 {
+            return node;
+        }
+
+        let material = hit.object().material();
+        let fresnel = material.reflective > 0.0 && material.transparency > 0.0;
+        let reflectance = if fresnel { hit.schlick() } else { 1.0 };
+        let transmittance = if fresnel { 1.0 - hit.schlick() } else { 1.0 };
+
+        if material.reflective > 0.0 {
+            node.children.push(self.inspect_child(
+                &hit,
+                RayKind::Reflection,
+                hit.over_point(),
+                hit.reflectv(),
+                hit.absorption1(),
+                material.reflective * reflectance,
+                remaining,
+            ));
+        }
+        if material.transparency > 0.0 {
+            if let Some(refractv) = hit.refractv() {
+                node.children.push(self.inspect_child(
+                    &hit,
+                    RayKind::Refraction,
+                    hit.under_point(),
+                    refractv,
+                    hit.absorption2(),
+                    material.transparency * transmittance,
+                    remaining,
+                ));
+            }
+        }
+        node
+    }
+
+    // The child's own colour, scaled by how much of it gets back through the
+    // medium it crosses and off the surface. A rough surface is followed along
+    // its mirror direction alone.
+    #[allow(clippy::too_many_arguments)]
+    fn inspect_child(
+        &self,
+        hit: &Intersection,
+        kind: RayKind,
+        origin: Float4,
+        direction: Float4,
+        absorption: Colour,
+        weight: Float,
+        remaining: u8,
+    ) -> PathNode {
+        let ray = Ray {
+            origin: origin.to_point3(),
+            direction: direction.to_vector3(),
+            wavelength: hit.wavelength(),
+            glossy_sample: hit.is_glossy_sample(),
+        };
+        let mut child = self.inspect_ray(&ray, kind, remaining - 1);
+        let distance = child.hit.as_ref().map_or(Float::INFINITY, |h| h.distance);
+        child.contribution = child.colour * beer_lambert(absorption, distance) * weight;
+        child
+    }
+}
+
+impl PathNode {
+    pub fn to_json(&self) -> String {
+        let mut s = String::new();
+        self.write_json(&mut s);
+        s
+    }
+
+    fn write_json(&self, s: &mut String) {
+        let kind = match self.kind {
+            RayKind::Primary => "primary",
+            RayKind::Reflection => "reflection",
+            RayKind::Refraction => "refraction",
+        };
+        // writing to a String cannot fail
+        let _ = write!(
+            s,
+            "{{\"kind\":\"{kind}\",\"origin\":{},\"direction\":{},\"colour\":{},\"contribution\":{},\"hit\":",
+            json_xyz(self.ray.origin.into()),
+            json_xyz(self.ray.direction.into()),
+            json_colour(self.colour),
+            json_colour(self.contribution),
+        );
+        match &self.hit {
+            Some(hit) => {
+                let object = hit.object.map_or("null".to_string(), |i| i.to_string());
+                let _ = write!(
+                    s,
+                    "{{\"object\":{object},\"distance\":{},\"point\":{},\"normal\":{},\"n1\":{},\"n2\":{},\"schlick\":{},\"shadow\":{}}}",
+                    json_number(hit.distance),
+                    json_xyz(hit.point),
+                    json_xyz(hit.normal),
+                    json_number(hit.n1),
+                    json_number(hit.n2),
+                    json_number(hit.schlick),
+                    json_colour(hit.shadow),
+                );
+            }
+            None => s.push_str("null"),
+        }
+        s.push_str(",\"children\":[");
+        for (i, child) in self.children.iter().enumerate() {
+            if i > 0 {
+                s.push(',');
+            }
+            child.write_json(s);
+        }
+        s.push_str("]}");
+    }
+
+    fn fmt_indented(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        let indent = "  ".repeat(depth);
+        let Point3 {
+            x: ox,
+            y: oy,
+            z: oz,
+        } = self.ray.origin;
+        let Vector3 {
+            x: dx,
+            y: dy,
+            z: dz,
+        } = self.ray.direction;
+        writeln!(
+            f,
+            "{indent}{:?} ray from ({ox:.4}, {oy:.4}, {oz:.4}) towards ({dx:.4}, {dy:.4}, {dz:.4})",
+            self.kind
+        )?;
+        match &self.hit {
+            Some(hit) => {
+                let object = hit.object.map_or("?".to_string(), |i| i.to_string());
+                writeln!(
+                    f,
+                    "{indent}  hit object {object} at distance {:.4}, n1 {:.4} n2 {:.4}, schlick {:.4}, shadow {}",
+                    hit.distance,
+                    hit.n1,
+                    hit.n2,
+                    hit.schlick,
+                    text_colour(hit.shadow)
+                )?;
+            }
+            None => writeln!(f, "{indent}  missed")?,
+        }
+        writeln!(
+            f,
+            "{indent}  colour {}, contributes {}",
+            text_colour(self.colour),
+            text_colour(self.contribution)
+        )?;
+        for child in self.children.iter() {
+            child.fmt_indented(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for PathNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+// JSON has no NaN or infinity, so they come out as null.
+fn json_number(x: Float) -> String {
+    if x.is_finite() {
+        x.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+fn json_xyz(v: Float4) -> String {
+    format!(
+        "[{},{},{}]",
+        json_number(v.0[0]),
+        json_number(v.0[1]),
+        json_number(v.0[2])
+    )
+}
+
+fn json_colour(c: Colour) -> String {
+    json_xyz(c.0)
+}
+
+fn text_colour(c: Colour) -> String {
+    let [r, g, b, _] = c.0.to_array();
+    format!("({r:.4}, {g:.4}, {b:.4})")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::consts::SQRT_2;
+    use crate::{
+        matrix::translate,
+        object::{Material, Object, Shape},
+        REF_RECURSION_LIMIT,
+    };
+
+    #[test]
+    fn inspect_miss_and_hit() {
+        let w = World::default();
+        let miss = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 1.0, 0.0));
+        let node = w.inspect(&miss, REF_RECURSION_LIMIT);
+        assert_eq!(node.hit, None);
+        assert!(node.children.is_empty());
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let node = w.inspect(&ray, REF_RECURSION_LIMIT);
+        assert_eq!(node.colour, w.colour_at(&ray, REF_RECURSION_LIMIT));
+        assert_eq!(node.contribution, node.colour);
+        let hit = node.hit.unwrap();
+        assert_eq!(hit.object, Some(0));
+        assert_eq!(hit.distance, 4.0);
+        assert_eq!(hit.shadow, Colour::white());
+        assert!(node.children.is_empty());
+    }
+
+    #[test]
+    fn inspect_reflection_and_refraction() {
+        let mut w = World::default();
+        w.objects.push(Object {
+            shape: Shape::Plane,
+            transform: translate(0.0, -1.0, 0.0).into(),
+            material: Material {
+                reflective: 0.5,
+                transparency: 0.5,
+                refractive_index: 1.5,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let ray = Ray::new(
+            Point3::new(0.0, 0.0, -3.0),
+            Vector3::new(0.0, -SQRT_2 / 2.0, SQRT_2 / 2.0),
+        );
+        let node = w.inspect(&ray, REF_RECURSION_LIMIT);
+        let hit = node.hit.as_ref().unwrap();
+        assert_eq!(hit.object, Some(2));
+        assert_eq!((hit.n1, hit.n2), (1.0, 1.5));
+        assert!(hit.schlick > 0.0 && hit.schlick < 1.0);
+
+        let kinds = node.children.iter().map(|c| c.kind).collect::<Vec<_>>();
+        assert_eq!(kinds, [RayKind::Reflection, RayKind::Refraction]);
+        let reflection = &node.children[0];
+        assert_eq!(
+            reflection.contribution,
+            reflection.colour * 0.5 * hit.schlick
+        );
+
+        let json = node.to_json();
+        assert!(json.starts_with("{\"kind\":\"primary\""));
+        assert_eq!(json.matches("\"kind\":").count(), count_nodes(&node));
+        assert!(node.to_string().contains("Refraction ray"));
+    }
+
+    #[test]
+    fn json_non_finite() {
+        let node = PathNode {
+            kind: RayKind::Primary,
+            ray: Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0)),
+            hit: None,
+            colour: Colour::new(Float::NAN, Float::INFINITY, 0.5),
+            contribution: Colour::black(),
+            children: vec![],
+        };
+        assert!(node
+            .to_json()
+            .contains("\"colour\":[null,null,0.5],\"contribution\":[0,0,0]"));
+    }
+
+    fn count_nodes(node: &PathNode) -> usize {
+        1 + node.children.iter().map(count_nodes).sum::<usize>()
+    }
+}
@@ -1,14 +1,26 @@
-#![feature(iter_intersperse)]
+#![cfg_attr(feature = "simd", feature(portable_simd))]
 
+pub mod bump;
 pub mod camera;
 pub mod canvas;
 pub mod colour;
+pub mod compare;
+pub mod environment;
 pub mod float4;
+pub mod inspect;
 pub mod matrix;
+pub mod medium;
+pub mod noise;
 pub mod object;
 pub mod pattern;
+pub mod quaternion;
 pub mod ray;
+pub mod sampling;
+pub mod spectrum;
+pub mod stats;
+pub mod tonemap;
 pub mod util;
+pub mod vector;
 pub mod world;
 
 const REF_RECURSION_LIMIT: u8 = 5;
@@ -1,7 +1,10 @@
-use crate::{float4::Float4, util::float_is_eq};
+use crate::{
+    float4::Float4,
+    util::{float_is_eq, Float},
+};
 
 #[derive(Debug, Clone)]
-pub struct Matrix(pub Vec<Vec<f64>>);
+pub struct Matrix(pub Vec<Vec<Float>>);
 
 impl Matrix {
     pub fn new(width: usize, height: usize) -> Self {
@@ -54,7 +57,7 @@ impl Matrix {
         sub
     }
 
-    pub fn determinant(&self) -> f64 {
+    pub fn determinant(&self) -> Float {
         assert_eq!(self.0.len(), self.0[0].len());
 
         if self.0.len() == 2 {
@@ -68,26 +71,34 @@ impl Matrix {
         }
     }
 
-    pub fn minor(&self, row: usize, col: usize) -> f64 {
+    pub fn minor(&self, row: usize, col: usize) -> Float {
         self.submatrix(row, col).determinant()
     }
 
-    pub fn cofactor(&self, row: usize, col: usize) -> f64 {
+    pub fn cofactor(&self, row: usize, col: usize) -> Float {
         let minor = self.minor(row, col);
         if (row + col) % 2 == 1 {
-            -1.0 * minor
+            -minor
         } else {
             minor
         }
     }
 
     pub fn inverse(&self) -> Option<Self> {
-        if float_is_eq(self.determinant(), 0.0) {
+        // The determinant scales with every row, so an absolute test would call
+        // a small but perfectly good scaling singular. Compare it with the
+        // largest it could be for rows of these lengths instead.
+        let det = self.determinant();
+        let bound: Float = self
+            .0
+            .iter()
+            .map(|row| row.iter().map(|e| e * e).sum::<Float>().sqrt())
+            .product();
+        if det.abs() <= bound * Float::EPSILON * self.0.len() as Float {
             return None;
         }
 
         let mut inverse = Self::new(self.0[0].len(), self.0.len());
-        let det = self.determinant();
         for row in 0..self.0.len() {
             for col in 0..self.0[0].len() {
                 inverse.0[col][row] = self.cofactor(row, col) / det;
@@ -97,6 +108,42 @@ impl Matrix {
     }
 }
 
+// A transform with its inverse, which is worked out once when it is made
+// rather than for every ray.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transform {
+    matrix: Matrix,
+    inverse: Matrix,
+}
+
+impl Transform {
+    // panics if `matrix` has no inverse
+    pub fn new(matrix: Matrix) -> Self {
+        let inverse = matrix.inverse().expect("transform has no inverse");
+        Self { matrix, inverse }
+    }
+
+    pub fn matrix(&self) -> &Matrix {
+        &self.matrix
+    }
+
+    pub fn inverse(&self) -> &Matrix {
+        &self.inverse
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::new(Matrix::identity(4))
+    }
+}
+
+impl From<Matrix> for Transform {
+    fn from(matrix: Matrix) -> Self {
+        Self::new(matrix)
+    }
+}
+
 impl std::ops::Mul<Matrix> for Matrix {
     type Output = Self;
 
@@ -109,8 +156,24 @@ impl std::ops::Mul<Float4> for Matrix {
     type Output = Float4;
 
     fn mul(self, rhs: Float4) -> Self::Output {
-        let rhs: Matrix = rhs.into();
-        (self * rhs).into()
+        &self * rhs
+    }
+}
+
+// One dot product per row, which the `simd` feature does in four lanes, and
+// without going through a one-column `Matrix`.
+impl std::ops::Mul<Float4> for &Matrix {
+    type Output = Float4;
+
+    fn mul(self, rhs: Float4) -> Self::Output {
+        assert!(self.0.len() == 4 && self.0[0].len() == 4);
+        let row = |i: usize| Float4::new(self.0[i][0], self.0[i][1], self.0[i][2], self.0[i][3]);
+        Float4::new(
+            row(0).dot(rhs),
+            row(1).dot(rhs),
+            row(2).dot(rhs),
+            row(3).dot(rhs),
+        )
     }
 }
 
@@ -141,7 +204,7 @@ impl From<Float4> for Matrix {
     }
 }
 
-pub fn translate(x: f64, y: f64, z: f64) -> Matrix {
+pub fn translate(x: Float, y: Float, z: Float) -> Matrix {
     Matrix(vec![
         vec![1.0, 0.0, 0.0, x],
         vec![0.0, 1.0, 0.0, y],
@@ -150,7 +213,7 @@ pub fn translate(x: f64, y: f64, z: f64) -> Matrix {
     ])
 }
 
-pub fn scale(x: f64, y: f64, z: f64) -> Matrix {
+pub fn scale(x: Float, y: Float, z: Float) -> Matrix {
     Matrix(vec![
         vec![x, 0.0, 0.0, 0.0],
         vec![0.0, y, 0.0, 0.0],
@@ -159,7 +222,7 @@ pub fn scale(x: f64, y: f64, z: f64) -> Matrix {
     ])
 }
 
-pub fn rotate_x(r: f64) -> Matrix {
+pub fn rotate_x(r: Float) -> Matrix {
     Matrix(vec![
         vec![1.0, 0.0, 0.0, 0.0],
         vec![0.0, r.cos(), -r.sin(), 0.0],
@@ -168,7 +231,7 @@ pub fn rotate_x(r: f64) -> Matrix {
     ])
 }
 
-pub fn rotate_y(r: f64) -> Matrix {
+pub fn rotate_y(r: Float) -> Matrix {
     Matrix(vec![
         vec![r.cos(), 0.0, r.sin(), 0.0],
         vec![0.0, 1.0, 0.0, 0.0],
@@ -177,7 +240,7 @@ pub fn rotate_y(r: f64) -> Matrix {
     ])
 }
 
-pub fn rotate_z(r: f64) -> Matrix {
+pub fn rotate_z(r: Float) -> Matrix {
     Matrix(vec![
         vec![r.cos(), -r.sin(), 0.0, 0.0],
         vec![r.sin(), r.cos(), 0.0, 0.0],
@@ -186,7 +249,7 @@ pub fn rotate_z(r: f64) -> Matrix {
     ])
 }
 
-pub fn shear(xy: f64, xz: f64, yx: f64, yz: f64, zx: f64, zy: f64) -> Matrix {
+pub fn shear(xy: Float, xz: Float, yx: Float, yz: Float, zx: Float, zy: Float) -> Matrix {
     Matrix(vec![
         vec![1.0, xy, xz, 0.0],
         vec![yx, 1.0, yz, 0.0],
@@ -214,7 +277,7 @@ pub fn view_transform(from: Float4, to: Float4, up: Float4) -> Matrix {
 mod test {
     use super::*;
     use crate::float4::*;
-    use std::f64::consts::PI;
+    use crate::util::consts::PI;
 
     #[test]
     fn identity_transpose() {
@@ -304,6 +367,25 @@ mod test {
         assert_eq!(matrix.inverse().unwrap(), expected);
     }
 
+    #[test]
+    fn inverse_singular() {
+        let matrix = Matrix(vec![
+            vec![-4.0, 2.0, -2.0, -3.0],
+            vec![9.0, 6.0, 2.0, 6.0],
+            vec![0.0, -5.0, 1.0, -5.0],
+            vec![0.0, 0.0, 0.0, 0.0],
+        ]);
+        assert!(matrix.inverse().is_none());
+        assert!(scale(1.0, 0.0, 1.0).inverse().is_none());
+
+        // tiny, but not singular, in either precision
+        let small = scale(0.01, 0.01, 0.01);
+        let point: Matrix = Float4::new_point(0.005, 0.0, 0.0).into();
+        let expected: Matrix = Float4::new_point(0.5, 0.0, 0.0).into();
+        assert_eq!(small.inverse().unwrap().multiply(&point), expected);
+        assert!(scale(1e-3, 1e-3, 1e-3).inverse().is_some());
+    }
+
     #[test]
     fn inverse_3() {
         let a = Matrix(vec![
@@ -383,7 +465,7 @@ mod test {
 
         let t = rotate_x(PI / 4.0);
         let expected: Matrix =
-            Float4::new_point(0.0, 1.0 / 2.0_f64.sqrt(), 1.0 / 2.0_f64.sqrt()).into();
+            Float4::new_point(0.0, 1.0 / Float::sqrt(2.0), 1.0 / Float::sqrt(2.0)).into();
         assert_eq!(t.multiply(&p), expected);
 
         let t = rotate_x(PI / 2.0);
@@ -392,7 +474,7 @@ mod test {
 
         let t = rotate_x(PI / 4.0).inverse().unwrap();
         let expected: Matrix =
-            Float4::new_point(0.0, 1.0 / 2.0_f64.sqrt(), -1.0 / 2.0_f64.sqrt()).into();
+            Float4::new_point(0.0, 1.0 / Float::sqrt(2.0), -1.0 / Float::sqrt(2.0)).into();
         assert_eq!(t.multiply(&p), expected);
     }
 
@@ -402,7 +484,7 @@ mod test {
 
         let t = rotate_y(PI / 4.0);
         let expected: Matrix =
-            Float4::new_point(1.0 / 2.0_f64.sqrt(), 0.0, 1.0 / 2.0_f64.sqrt()).into();
+            Float4::new_point(1.0 / Float::sqrt(2.0), 0.0, 1.0 / Float::sqrt(2.0)).into();
         assert_eq!(t.multiply(&p), expected);
 
         let t = rotate_y(PI / 2.0);
@@ -416,7 +498,7 @@ mod test {
 
         let t = rotate_z(PI / 4.0);
         let expected: Matrix =
-            Float4::new_point(-1.0 / 2.0_f64.sqrt(), 1.0 / 2.0_f64.sqrt(), 0.0).into();
+            Float4::new_point(-1.0 / Float::sqrt(2.0), 1.0 / Float::sqrt(2.0), 0.0).into();
         assert_eq!(t.multiply(&p), expected);
 
         let t = rotate_z(PI / 2.0);
@@ -0,0 +1,84 @@
+use crate::colour::Colour;
+use crate::util::{consts::PI, Float};
+
+// transmittance below which a medium is treated as opaque
+const OPAQUE_TRANSMITTANCE: Float = 1e-3;
+
+// A homogeneous participating medium.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Medium {
+    // extinction coefficient per unit distance
+    pub density: Float,
+    // fraction of the extinguished light that is scattered rather than absorbed
+    pub albedo: Colour,
+    // Henyey-Greenstein g: negative scatters backwards, positive forwards
+    pub anisotropy: Float,
+}
+
+impl Medium {
+    pub fn transmittance(&self, distance: Float) -> Float {
+        (-self.density * distance).exp()
+    }
+
+    // `cos_theta` is between the light's direction of travel and the scattered direction
+    pub fn phase(&self, cos_theta: Float) -> Float {
+        let g = self.anisotropy;
+        (1.0 - g * g) / (4.0 * PI * (1.0 + g * g - 2.0 * g * cos_theta).powf(1.5))
+    }
+
+    // distance past which almost no light makes it through
+    pub fn extent(&self) -> Float {
+        if self.density <= 0.0 {
+            0.0
+        } else {
+            -OPAQUE_TRANSMITTANCE.ln() / self.density
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::float_is_eq;
+
+    #[test]
+    fn transmittance() {
+        let fog = Medium {
+            density: 0.5,
+            albedo: Colour::white(),
+            anisotropy: 0.0,
+        };
+        assert!(float_is_eq(fog.transmittance(0.0), 1.0));
+        assert!(float_is_eq(fog.transmittance(2.0), Float::exp(-1.0)));
+        assert!(float_is_eq(
+            fog.transmittance(fog.extent()),
+            OPAQUE_TRANSMITTANCE
+        ));
+    }
+
+    #[test]
+    fn phase() {
+        let isotropic = Medium {
+            density: 1.0,
+            albedo: Colour::white(),
+            anisotropy: 0.0,
+        };
+        assert!(float_is_eq(isotropic.phase(0.3), 1.0 / (4.0 * PI)));
+
+        let forward = Medium {
+            anisotropy: 0.7,
+            ..isotropic
+        };
+        assert!(forward.phase(1.0) > forward.phase(-1.0));
+
+        // integrates to one over the sphere
+        let steps = 10000;
+        let integral = (0..steps)
+            .map(|i| {
+                let cos = -1.0 + 2.0 * (i as Float + 0.5) / steps as Float;
+                forward.phase(cos) * 2.0 * PI * 2.0 / steps as Float
+            })
+            .sum::<Float>();
+        assert!((integral - 1.0).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,246 @@
+use crate::float4::Float4;
+use crate::util::Float;
+
+// Offsets used to decorrelate the three components of a jitter displacement.
+const JITTER_OFFSET_Y: Float = 31.416;
+const JITTER_OFFSET_Z: Float = 47.853;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Perlin {
+    pub seed: u64,
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    pub fn at(&self, point: Float4) -> Float {
+        self.noise(point.0[0], point.0[1], point.0[2])
+    }
+
+    fn noise(&self, x: Float, y: Float, z: Float) -> Float {
+        let (xi, yi, zi) = (x.floor() as i64, y.floor() as i64, z.floor() as i64);
+        let (xf, yf, zf) = (x - x.floor(), y - y.floor(), z - z.floor());
+        let (u, v, w) = (fade(xf), fade(yf), fade(zf));
+
+        let g = |dx: i64, dy: i64, dz: i64| {
+            let h = self.hash(xi + dx, yi + dy, zi + dz);
+            grad(h, xf - dx as Float, yf - dy as Float, zf - dz as Float)
+        };
+
+        let x00 = lerp(u, g(0, 0, 0), g(1, 0, 0));
+        let x10 = lerp(u, g(0, 1, 0), g(1, 1, 0));
+        let x01 = lerp(u, g(0, 0, 1), g(1, 0, 1));
+        let x11 = lerp(u, g(0, 1, 1), g(1, 1, 1));
+
+        lerp(w, lerp(v, x00, x10), lerp(v, x01, x11))
+    }
+
+    fn octaves(&self, point: Float4, octaves: u32, f: impl Fn(Float) -> Float) -> Float {
+        let (mut frequency, mut amplitude) = (1.0, 1.0);
+        let mut total = 0.0;
+        for _ in 0..octaves {
+            let n = self.noise(
+                point.0[0] * frequency,
+                point.0[1] * frequency,
+                point.0[2] * frequency,
+            );
+            total += f(n) * amplitude;
+            frequency *= 2.0;
+            amplitude *= 0.5;
+        }
+        total
+    }
+
+    pub fn fbm(&self, point: Float4, octaves: u32) -> Float {
+        self.octaves(point, octaves, |n| n)
+    }
+
+    pub fn turbulence(&self, point: Float4, octaves: u32) -> Float {
+        self.octaves(point, octaves, Float::abs)
+    }
+
+    // splitmix64 finaliser over the lattice coordinates and the seed
+    fn hash(&self, x: i64, y: i64, z: i64) -> u64 {
+        let mut h = self.seed
+            ^ (x as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+            ^ (y as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f)
+            ^ (z as u64).wrapping_mul(0x1656_67b1_9e37_79f9);
+        h ^= h >> 30;
+        h = h.wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        h ^= h >> 27;
+        h = h.wrapping_mul(0x94d0_49bb_1331_11eb);
+        h ^ (h >> 31)
+    }
+}
+
+fn fade(t: Float) -> Float {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: Float, a: Float, b: Float) -> Float {
+    a + t * (b - a)
+}
+
+fn grad(hash: u64, x: Float, y: Float, z: Float) -> Float {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Noise {
+    pub perlin: Perlin,
+    // how strongly the noise shows in a pattern or bump
+    pub amplitude: Float,
+    // how far `jitter` moves a point
+    pub displacement: Float,
+    // lattice cells per unit of the first octave, so features are 1 / frequency across
+    pub frequency: Float,
+    pub octaves: u32,
+}
+
+impl Noise {
+    // Leaves points where they are until given a displacement
+    pub fn new(seed: u64, amplitude: Float, octaves: u32) -> Self {
+        Self {
+            perlin: Perlin::new(seed),
+            amplitude,
+            displacement: 0.0,
+            frequency: 1.0,
+            octaves,
+        }
+    }
+
+    pub fn with_displacement(mut self, displacement: Float) -> Self {
+        self.displacement = displacement;
+        self
+    }
+
+    pub fn fbm(&self, point: Float4) -> Float {
+        self.perlin.fbm(self.lattice_point(point), self.octaves)
+    }
+
+    pub fn turbulence(&self, point: Float4) -> Float {
+        self.perlin
+            .turbulence(self.lattice_point(point), self.octaves)
+    }
+
+    fn lattice_point(&self, point: Float4) -> Float4 {
+        let [x, y, z, _] = point.to_array();
+        let f = self.frequency;
+        Float4::new_point(x * f, y * f, z * f)
+    }
+
+    pub fn jitter(&self, point: Float4) -> Float4 {
+        let offset = |d: Float| Float4::new_point(point.0[0] + d, point.0[1] + d, point.0[2] + d);
+        let displacement = Float4::new_vector(
+            self.fbm(point),
+            self.fbm(offset(JITTER_OFFSET_Y)),
+            self.fbm(offset(JITTER_OFFSET_Z)),
+        );
+        point + displacement.scalar_mul(self.displacement)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::float_is_eq;
+
+    #[test]
+    fn perlin_lattice() {
+        let p = Perlin::new(7);
+        assert!(float_is_eq(p.at(Float4::origin()), 0.0));
+        assert!(float_is_eq(p.at(Float4::new_point(3.0, -2.0, 5.0)), 0.0));
+    }
+
+    #[test]
+    fn perlin_seeded() {
+        let point = Float4::new_point(0.3, 1.7, -2.2);
+        assert_eq!(Perlin::new(1).at(point), Perlin::new(1).at(point));
+        assert!(!float_is_eq(
+            Perlin::new(1).at(point),
+            Perlin::new(2).at(point)
+        ));
+    }
+
+    #[test]
+    fn perlin_range() {
+        let p = Perlin::new(42);
+        for i in 0..1000 {
+            let f = i as Float * 0.137;
+            let n = p.at(Float4::new_point(f, f * 0.71, -f * 1.3));
+            assert!((-1.5..=1.5).contains(&n));
+        }
+    }
+
+    #[test]
+    fn noise_frequency() {
+        let point = Float4::new_point(0.3, 0.4, 0.5);
+        let noise = Noise::new(3, 1.0, 4);
+        let fine = Noise {
+            frequency: 2.0,
+            ..noise
+        };
+        let doubled = Float4::new_point(0.6, 0.8, 1.0);
+        assert!(float_is_eq(fine.fbm(point), noise.fbm(doubled)));
+        assert!(float_is_eq(
+            fine.turbulence(point),
+            noise.turbulence(doubled)
+        ));
+    }
+
+    #[test]
+    fn jitter() {
+        let point = Float4::new_point(0.4, 0.5, 0.6);
+        assert_eq!(Noise::new(3, 1.0, 4).jitter(point), point);
+
+        let jittered = Noise::new(3, 1.0, 4).with_displacement(0.5).jitter(point);
+        assert!(jittered.is_point());
+        assert_ne!(jittered, point);
+        assert!((jittered - point).mag() < 0.5 * 2.0 * Float::sqrt(3.0));
+
+        // the amplitude is for patterns and bumps, and does not move points
+        let louder = Noise::new(3, 4.0, 4).with_displacement(0.5);
+        assert_eq!(louder.jitter(point), jittered);
+    }
+
+    #[test]
+    fn noise_between_lattice_points() {
+        let noise = Noise::new(17, 1.0, 4);
+        // the octaves halve in amplitude, so four of them at most add up to
+        // 1.875 times the range of a single one
+        let bound = 1.5 * 1.875;
+        for i in 0..500 {
+            let f = i as Float * 0.173 + 0.05;
+            let point = Float4::new_point(f, -f * 0.59, f * 1.31);
+            let fbm = noise.fbm(point);
+            let turbulence = noise.turbulence(point);
+            assert!((-bound..=bound).contains(&fbm));
+            assert!((0.0..=bound).contains(&turbulence));
+
+            let same = Noise::new(17, 1.0, 4);
+            assert_eq!(same.fbm(point), fbm);
+            assert_eq!(same.turbulence(point), turbulence);
+            assert_eq!(
+                same.with_displacement(0.3).jitter(point),
+                noise.with_displacement(0.3).jitter(point)
+            );
+        }
+        let point = Float4::new_point(0.35, 1.6, -0.45);
+        assert!(!float_is_eq(noise.fbm(point), 0.0));
+        assert!(!float_is_eq(
+            noise.fbm(point),
+            Noise::new(18, 1.0, 4).fbm(point)
+        ));
+    }
+}
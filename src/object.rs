@@ -1,12 +1,20 @@
 use crate::{
+    bump::{Bump, UvMapping},
     colour::Colour,
     float4::Float4,
-    matrix::Matrix,
+    matrix::{Matrix, Transform},
+    medium::Medium,
     pattern::Pattern,
     ray::{Intersection, Intersections, Ray},
-    util::EPSILON,
+    spectrum::Cauchy,
+    stats,
+    util::{consts::PI, Float, EPSILON},
+    vector::Point3,
 };
 
+// GGX degenerates into a delta lobe as roughness goes to zero
+const MIN_GGX_ROUGHNESS: Float = 0.05;
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Shape {
     Sphere,
@@ -16,24 +24,55 @@ pub enum Shape {
 #[derive(Debug, Clone, PartialEq)]
 pub struct Object {
     pub shape: Shape,
-    pub transform: Matrix,
+    pub transform: Transform,
     pub material: Material,
+    pub casts_shadow: bool,
+}
+
+// A unit sphere at the origin
+impl Default for Object {
+    fn default() -> Self {
+        Self {
+            shape: Shape::Sphere,
+            transform: Transform::default(),
+            material: Material::default(),
+            casts_shadow: true,
+        }
+    }
 }
 
 impl Object {
     pub fn transform(&self) -> &Matrix {
-        &self.transform
+        self.transform.matrix()
     }
 
     pub fn material(&self) -> &Material {
         &self.material
     }
 
-    pub fn intersect(&self, ray: &Ray) -> Intersections {
-        let object_space_ray = ray.transform(self.transform().inverse().unwrap());
-        let distances = match self.shape {
+    pub fn inverse(&self) -> &Matrix {
+        self.transform.inverse()
+    }
+
+    pub fn intersect(&self, ray: &Ray) -> Intersections<'_> {
+        Intersections::new(
+            self.distances(ray)
+                .map(|distance| Intersection::new(ray, self, distance))
+                .collect(),
+        )
+    }
+
+    // Where `ray` crosses the surface, without any of the shading data. There
+    // are at most two crossings, so nothing is allocated for them.
+    pub fn distances(&self, ray: &Ray) -> impl Iterator<Item = Float> {
+        let object_space_ray = ray.transform(self.inverse());
+        stats::record(|c| match self.shape {
+            Shape::Sphere => c.sphere_tests += 1,
+            Shape::Plane => c.plane_tests += 1,
+        });
+        let (distances, count) = match self.shape {
             Shape::Sphere => {
-                let sphere_to_ray = object_space_ray.origin - Float4::origin();
+                let sphere_to_ray = object_space_ray.origin - Point3::origin();
 
                 let a = object_space_ray.direction.dot(object_space_ray.direction);
                 let b = 2.0 * object_space_ray.direction.dot(sphere_to_ray);
@@ -42,43 +81,69 @@ impl Object {
                 let discriminant = b.powi(2) - 4.0 * a * c;
 
                 if discriminant < 0.0 {
-                    vec![]
+                    ([0.0; 2], 0)
                 } else {
                     let t1 = (-b - discriminant.sqrt()) / (2.0 * a);
                     let t2 = (-b + discriminant.sqrt()) / (2.0 * a);
-                    vec![t1, t2]
+                    ([t1, t2], 2)
                 }
             }
             Shape::Plane => {
-                if object_space_ray.direction.0[1].abs() < EPSILON {
-                    vec![]
+                if object_space_ray.direction.y.abs() < EPSILON {
+                    ([0.0; 2], 0)
                 } else {
-                    let t = -object_space_ray.origin.0[1] / object_space_ray.direction.0[1];
-                    vec![t]
+                    let t = -object_space_ray.origin.y / object_space_ray.direction.y;
+                    ([t, 0.0], 1)
                 }
             }
         };
-
-        Intersections::new(
-            distances
-                .iter()
-                .map(|distance| Intersection::new(ray, self, *distance))
-                .collect(),
-        )
+        distances.into_iter().take(count)
     }
 
     pub fn normal_at(&self, world_point: Float4) -> Float4 {
-        let matrix = self.transform();
-        let object_point = matrix.inverse().unwrap() * world_point;
+        self.normals_at(world_point).1
+    }
+
+    // The normal of the surface itself and the one to shade with, which differ
+    // when the material has a bump. Both are in world space.
+    pub fn normals_at(&self, world_point: Float4) -> (Float4, Float4) {
+        let inverse = self.inverse();
+        let object_point = inverse * world_point;
 
         let object_normal = match self.shape {
             Shape::Sphere => object_point - Float4::origin(),
             Shape::Plane => Float4::new_vector(0.0, 1.0, 0.0),
         };
+        let shading_normal = self
+            .material
+            .bump
+            .as_ref()
+            .map(|bump| bump.perturb(object_point, object_normal));
+
+        let to_world = |normal: Float4| Float4::from(inverse.transform_normal(normal.to_vector3()));
+        let geometric = to_world(object_normal);
+        (geometric, shading_normal.map_or(geometric, to_world))
+    }
+
+    pub fn colour_at(&self, point: Float4) -> Colour {
+        self.material()
+            .pattern
+            .as_ref()
+            .map_or(self.material.colour, |pattern| {
+                pattern.at_object(point, self)
+            })
+    }
 
-        let mut world_normal = matrix.inverse().unwrap().transpose() * object_normal;
-        world_normal.0[3] = 0.0;
-        world_normal.normalise()
+    // Texture coordinates of a world-space point, using a normal map's mapping
+    // if there is one and the natural one for the shape otherwise.
+    pub fn uv_at(&self, point: Float4) -> (Float, Float) {
+        let object_point = self.inverse() * point;
+        let mapping = match (&self.material.bump, self.shape) {
+            (Some(Bump::NormalMap { mapping, .. }), _) => *mapping,
+            (_, Shape::Sphere) => UvMapping::Spherical,
+            (_, Shape::Plane) => UvMapping::Planar,
+        };
+        mapping.uv(object_point)
     }
 
     pub fn lighting(
@@ -89,42 +154,96 @@ impl Object {
         normalv: Float4,
         in_shadow: bool,
     ) -> Colour {
-        let colour = self
-            .material()
-            .pattern
-            .as_ref()
-            .map_or(self.material.colour, |pattern| {
-                pattern.at_object(point, self)
-            });
-
-        let effective_colour = colour * light.colour;
-        let ambient = effective_colour.scalar_product(self.material.ambient);
+        let ambient = self.ambient(light, point);
 
         if in_shadow {
             return ambient;
         }
 
+        ambient + self.direct_lighting(light, point, eyev, normalv)
+    }
+
+    pub fn ambient(&self, light: PointLight, point: Float4) -> Colour {
+        (self.colour_at(point) * light.colour).scalar_product(self.material.ambient)
+    }
+
+    // diffuse and specular terms only
+    pub fn direct_lighting(
+        &self,
+        light: PointLight,
+        point: Float4,
+        eyev: Float4,
+        normalv: Float4,
+    ) -> Colour {
+        let colour = self.colour_at(point);
         let lightv = (light.position - point).normalise();
         let light_dot_normal = lightv.dot(normalv);
-        let (diffuse, specular) = if light_dot_normal < 0.0 {
-            (Colour::black(), Colour::black())
-        } else {
-            let diffuse = effective_colour.scalar_product(self.material.diffuse * light_dot_normal);
-
-            let reflectv = (-lightv).reflect(normalv);
-            let reflect_dot_eye = reflectv.dot(eyev);
-            let specular = if reflect_dot_eye <= 0.0 {
-                Colour::black()
-            } else {
-                let factor = reflect_dot_eye.powf(self.material.shininess);
-                light.colour.scalar_product(self.material.specular * factor)
-            };
-
-            (diffuse, specular)
+        if light_dot_normal < 0.0 {
+            return Colour::black();
+        }
+
+        let factor = match self.material.shading {
+            ShadingModel::Phong => {
+                let reflectv = (-lightv).reflect(normalv);
+                reflectv.dot(eyev).max(0.0).powf(self.material.shininess)
+            }
+            ShadingModel::BlinnPhong => {
+                let halfv = (lightv + eyev).normalise();
+                halfv.dot(normalv).max(0.0).powf(self.material.shininess)
+            }
+            ShadingModel::Ggx { metallic } => {
+                return self.ggx(colour, light.colour, lightv, eyev, normalv, metallic);
+            }
         };
 
-        ambient + diffuse + specular
+        let effective_colour = colour * light.colour;
+        let diffuse = effective_colour.scalar_product(self.material.diffuse * light_dot_normal);
+        let specular = light.colour.scalar_product(self.material.specular * factor);
+        diffuse + specular
     }
+
+    // Cook-Torrance with a GGX distribution, Smith-Schlick geometry and Schlick
+    // Fresnel. Scaled by pi so a white Lambertian surface matches Phong's diffuse.
+    fn ggx(
+        &self,
+        base: Colour,
+        light_colour: Colour,
+        lightv: Float4,
+        eyev: Float4,
+        normalv: Float4,
+        metallic: Float,
+    ) -> Colour {
+        let n_dot_l = lightv.dot(normalv).max(0.0);
+        let n_dot_v = eyev.dot(normalv).max(EPSILON);
+        let halfv = (lightv + eyev).normalise();
+        let n_dot_h = halfv.dot(normalv).max(0.0);
+        let v_dot_h = halfv.dot(eyev).max(0.0);
+
+        let alpha = self.material.roughness.max(MIN_GGX_ROUGHNESS).powi(2);
+        let alpha2 = alpha * alpha;
+        let d = alpha2 / (PI * (n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0).powi(2));
+
+        let k = alpha / 2.0;
+        let g1 = |x: Float| x / (x * (1.0 - k) + k);
+        let g = g1(n_dot_l) * g1(n_dot_v);
+
+        let dielectric = Colour::new(0.04, 0.04, 0.04);
+        let f0 = dielectric + (base - dielectric) * metallic;
+        let f = f0 + (Colour::white() - f0) * (1.0 - v_dot_h).powi(5);
+
+        let specular = f * (d * g / (4.0 * n_dot_l.max(EPSILON) * n_dot_v));
+        let diffuse = (Colour::white() - f) * base * (1.0 - metallic) * (1.0 / PI);
+
+        (diffuse + specular) * light_colour * (PI * n_dot_l)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShadingModel {
+    Phong,
+    BlinnPhong,
+    // uses `Material::roughness`
+    Ggx { metallic: Float },
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -136,14 +255,33 @@ pub struct PointLight {
 #[derive(Debug, Clone, PartialEq)]
 pub struct Material {
     pub colour: Colour,
-    pub ambient: f64,
-    pub diffuse: f64,
-    pub specular: f64,
-    pub shininess: f64,
-    pub reflective: f64,
-    pub transparency: f64,
-    pub refractive_index: f64,
+    pub ambient: Float,
+    pub diffuse: Float,
+    pub specular: Float,
+    pub shininess: Float,
+    pub reflective: Float,
+    pub transparency: Float,
+    pub refractive_index: Float,
+    // overrides `refractive_index` for rays of a single wavelength
+    pub dispersion: Option<Cauchy>,
+    // Beer-Lambert coefficient per unit distance travelled inside the object
+    pub absorption: Colour,
+    pub roughness: Float,
+    // rays averaged over a rough surface when it is the first rough one on their path
+    pub glossy_samples: usize,
+    pub shading: ShadingModel,
+    pub emissive: Colour,
     pub pattern: Option<Pattern>,
+    pub bump: Option<Bump>,
+    // makes the object an invisible boundary around a volume of this medium
+    pub medium: Option<Medium>,
+}
+
+impl Material {
+    // blocks light completely, rather than filtering or thinning it
+    pub fn is_opaque(&self) -> bool {
+        self.transparency <= 0.0 && self.medium.is_none()
+    }
 }
 
 impl Default for Material {
@@ -157,7 +295,15 @@ impl Default for Material {
             reflective: 0.0,
             transparency: 0.0,
             refractive_index: 1.0,
+            dispersion: None,
+            absorption: Colour::black(),
+            roughness: 0.0,
+            glossy_samples: 16,
+            shading: ShadingModel::Phong,
+            emissive: Colour::black(),
             pattern: None,
+            bump: None,
+            medium: None,
         }
     }
 }
@@ -166,13 +312,15 @@ impl Default for Material {
 mod test {
     use super::*;
     use crate::pattern::PatternKind;
+    use crate::util::float_is_eq;
 
     #[test]
     fn material_lighting() {
         let s = Object {
             shape: Shape::Sphere,
-            transform: Matrix::identity(4),
+            transform: Matrix::identity(4).into(),
             material: Material::default(),
+            ..Default::default()
         };
         let position = Float4::origin();
 
@@ -187,7 +335,7 @@ mod test {
             Colour::new(1.9, 1.9, 1.9)
         );
 
-        let eyev = Float4::new_vector(0.0, 1.0 / 2_f64.sqrt(), -1.0 / 2_f64.sqrt());
+        let eyev = Float4::new_vector(0.0, 1.0 / Float::sqrt(2.0), -1.0 / Float::sqrt(2.0));
         let normalv = Float4::new_vector(0.0, 0.0, -1.0);
         let light = PointLight {
             position: Float4::new_point(0.0, 0.0, -10.0),
@@ -209,7 +357,7 @@ mod test {
             Colour::new(0.7364, 0.7364, 0.7364)
         );
 
-        let eyev = Float4::new_vector(0.0, -1.0 / 2_f64.sqrt(), -1.0 / 2_f64.sqrt());
+        let eyev = Float4::new_vector(0.0, -1.0 / Float::sqrt(2.0), -1.0 / Float::sqrt(2.0));
         let normalv = Float4::new_vector(0.0, 0.0, -1.0);
         let light = PointLight {
             position: Float4::new_point(0.0, 10.0, -10.0),
@@ -243,11 +391,93 @@ mod test {
         );
     }
 
+    #[test]
+    fn blinn_phong_lighting() {
+        let s = Object {
+            shape: Shape::Sphere,
+            transform: Matrix::identity(4).into(),
+            material: Material {
+                shading: ShadingModel::BlinnPhong,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let position = Float4::origin();
+        let normalv = Float4::new_vector(0.0, 0.0, -1.0);
+        let light = PointLight {
+            position: Float4::new_point(0.0, 0.0, -10.0),
+            colour: Colour::white(),
+        };
+
+        let eyev = Float4::new_vector(0.0, 0.0, -1.0);
+        assert_eq!(
+            s.lighting(light, position, eyev, normalv, false),
+            Colour::new(1.9, 1.9, 1.9)
+        );
+
+        // the half vector stays closer to the normal than Phong's reflection vector
+        let eyev = Float4::new_vector(0.0, 1.0 / Float::sqrt(2.0), -1.0 / Float::sqrt(2.0));
+        let blinn = s.lighting(light, position, eyev, normalv, false);
+        assert!(blinn.0 .0[0] > 1.0 && blinn.0 .0[0] < 1.9);
+    }
+
+    #[test]
+    fn ggx_lighting() {
+        let dielectric = Object {
+            shape: Shape::Sphere,
+            transform: Matrix::identity(4).into(),
+            material: Material {
+                roughness: 0.5,
+                shading: ShadingModel::Ggx { metallic: 0.0 },
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let position = Float4::origin();
+        let eyev = Float4::new_vector(0.0, 0.0, -1.0);
+        let normalv = Float4::new_vector(0.0, 0.0, -1.0);
+        let light = PointLight {
+            position: Float4::new_point(0.0, 0.0, -10.0),
+            colour: Colour::white(),
+        };
+        assert_eq!(
+            dielectric.lighting(light, position, eyev, normalv, false),
+            Colour::new(1.22, 1.22, 1.22)
+        );
+        assert_eq!(
+            dielectric.lighting(light, position, eyev, normalv, true),
+            Colour::new(0.1, 0.1, 0.1)
+        );
+
+        let metal = Object {
+            material: Material {
+                colour: Colour::new(1.0, 0.5, 0.25),
+                roughness: 0.5,
+                shading: ShadingModel::Ggx { metallic: 1.0 },
+                ..Default::default()
+            },
+            ..dielectric
+        };
+        assert_eq!(
+            metal.lighting(light, position, eyev, normalv, false),
+            Colour::new(4.1, 2.05, 1.025)
+        );
+
+        let behind = PointLight {
+            position: Float4::new_point(0.0, 0.0, 10.0),
+            colour: Colour::white(),
+        };
+        assert_eq!(
+            metal.lighting(behind, position, eyev, normalv, false),
+            Colour::new(0.1, 0.05, 0.025)
+        );
+    }
+
     #[test]
     fn material_lighting_with_pattern() {
         let s = Object {
             shape: Shape::Sphere,
-            transform: Matrix::identity(4),
+            transform: Matrix::identity(4).into(),
             material: Material {
                 ambient: 1.0,
                 diffuse: 0.0,
@@ -255,9 +485,11 @@ mod test {
                 pattern: Some(Pattern {
                     kind: PatternKind::Stripe(Colour::white(), Colour::black()),
                     transform: Matrix::identity(4),
+                    jitter: None,
                 }),
                 ..Default::default()
             },
+            ..Default::default()
         };
         let eyev = Float4::new_vector(0.0, 0.0, -1.0);
         let normalv = Float4::new_vector(0.0, 0.0, -1.0);
@@ -287,4 +519,43 @@ mod test {
             Colour::black()
         );
     }
+
+    #[test]
+    fn uv_at() {
+        use crate::matrix::translate;
+
+        let plane = Object {
+            shape: Shape::Plane,
+            transform: translate(0.0, 1.0, 0.0).into(),
+            material: Material::default(),
+            ..Default::default()
+        };
+        let (u, v) = plane.uv_at(Float4::new_point(2.25, 1.0, -0.5));
+        assert!(float_is_eq(u, 0.25) && float_is_eq(v, 0.5));
+
+        let sphere = Object {
+            shape: Shape::Sphere,
+            transform: translate(5.0, 0.0, 0.0).into(),
+            material: Material::default(),
+            ..Default::default()
+        };
+        let (u, v) = sphere.uv_at(Float4::new_point(5.0, 1.0, 0.0));
+        assert!(float_is_eq(u, 0.5) && float_is_eq(v, 1.0));
+    }
+
+    #[test]
+    fn object_inverse() {
+        use crate::matrix::{scale, translate};
+
+        let mut sphere = Object {
+            transform: translate(1.0, 0.0, 0.0).into(),
+            ..Default::default()
+        };
+        assert_eq!(*sphere.inverse(), translate(-1.0, 0.0, 0.0));
+
+        // a new transform brings its own inverse
+        sphere.transform = scale(2.0, 2.0, 2.0).into();
+        assert_eq!(*sphere.inverse(), scale(0.5, 0.5, 0.5));
+        assert_eq!(*sphere.transform(), scale(2.0, 2.0, 2.0));
+    }
 }
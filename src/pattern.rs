@@ -1,4 +1,6 @@
-use crate::{colour::Colour, float4::Float4, matrix::Matrix, object::Object, util::float_is_eq};
+use crate::{
+    colour::Colour, float4::Float4, matrix::Matrix, noise::Noise, object::Object, util::float_is_eq,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum PatternKind {
@@ -6,6 +8,9 @@ pub enum PatternKind {
     Gradient(Colour, Colour),
     Ring(Colour, Colour),
     Checkers(Colour, Colour),
+    Marble(Colour, Colour, Noise),
+    Wood(Colour, Colour, Noise),
+    Turbulence(Colour, Colour, Noise),
     TestLocation,
 }
 
@@ -13,10 +18,12 @@ pub enum PatternKind {
 pub struct Pattern {
     pub kind: PatternKind,
     pub transform: Matrix,
+    pub jitter: Option<Noise>,
 }
 
 impl Pattern {
     pub fn at(&self, point: Float4) -> Colour {
+        let point = self.jitter.map_or(point, |noise| noise.jitter(point));
         match self.kind {
             PatternKind::Stripe(colour1, colour2) => {
                 if float_is_eq(point.0[0].floor() % 2.0, 0.0) {
@@ -49,12 +56,25 @@ impl Pattern {
                     colour2
                 }
             }
+            PatternKind::Marble(colour1, colour2, noise) => {
+                let t = (point.0[0] + noise.amplitude * noise.turbulence(point)).sin();
+                colour1 + (colour2 - colour1) * (0.5 + 0.5 * t)
+            }
+            PatternKind::Wood(colour1, colour2, noise) => {
+                let r = (point.0[0].powi(2) + point.0[2].powi(2)).sqrt()
+                    + noise.amplitude * noise.fbm(point);
+                colour1 + (colour2 - colour1) * (r - r.floor())
+            }
+            PatternKind::Turbulence(colour1, colour2, noise) => {
+                let t = (noise.amplitude * noise.turbulence(point)).min(1.0);
+                colour1 + (colour2 - colour1) * t
+            }
             PatternKind::TestLocation => Colour::new(point.0[0], point.0[1], point.0[2]),
         }
     }
 
     pub fn at_object(&self, point: Float4, object: &Object) -> Colour {
-        let object_point = object.transform().inverse().unwrap() * point;
+        let object_point = object.inverse() * point;
         let pattern_point = self.transform.inverse().unwrap() * object_point;
         self.at(pattern_point)
     }
@@ -74,6 +94,7 @@ mod test {
         let s = Pattern {
             kind: PatternKind::Stripe(Colour::white(), Colour::black()),
             transform: Matrix::identity(4),
+            jitter: None,
         };
 
         assert_eq!(s.at(Float4::origin()), Colour::white());
@@ -95,12 +116,14 @@ mod test {
     fn stripe_at_object() {
         let s1 = Object {
             shape: Shape::Sphere,
-            transform: scale(2.0, 2.0, 2.0),
+            transform: scale(2.0, 2.0, 2.0).into(),
             material: Material::default(),
+            ..Default::default()
         };
         let p1 = Pattern {
             kind: PatternKind::Stripe(Colour::white(), Colour::black()),
             transform: Matrix::identity(4),
+            jitter: None,
         };
         assert_eq!(
             p1.at_object(Float4::new_point(1.5, 0.0, 0.0), &s1),
@@ -109,12 +132,14 @@ mod test {
 
         let s2 = Object {
             shape: Shape::Sphere,
-            transform: Matrix::identity(4),
+            transform: Matrix::identity(4).into(),
             material: Material::default(),
+            ..Default::default()
         };
         let p2 = Pattern {
             kind: PatternKind::Stripe(Colour::white(), Colour::black()),
             transform: scale(2.0, 2.0, 2.0),
+            jitter: None,
         };
         assert_eq!(
             p2.at_object(Float4::new_point(1.5, 0.0, 0.0), &s2),
@@ -123,12 +148,14 @@ mod test {
 
         let s3 = Object {
             shape: Shape::Sphere,
-            transform: scale(2.0, 2.0, 2.0),
+            transform: scale(2.0, 2.0, 2.0).into(),
             material: Material::default(),
+            ..Default::default()
         };
         let p3 = Pattern {
             kind: PatternKind::Stripe(Colour::white(), Colour::black()),
             transform: scale(0.5, 0.5, 0.5),
+            jitter: None,
         };
         assert_eq!(
             p3.at_object(Float4::new_point(2.5, 0.0, 0.0), &s3),
@@ -137,12 +164,14 @@ mod test {
 
         let s4 = Object {
             shape: Shape::Sphere,
-            transform: scale(2.0, 2.0, 2.0),
+            transform: scale(2.0, 2.0, 2.0).into(),
             material: Material::default(),
+            ..Default::default()
         };
         let p4 = Pattern {
             kind: PatternKind::TestLocation,
             transform: Matrix::identity(4),
+            jitter: None,
         };
         assert_eq!(
             p4.at_object(Float4::new_point(2.0, 3.0, 4.0), &s4),
@@ -151,12 +180,14 @@ mod test {
 
         let s5 = Object {
             shape: Shape::Sphere,
-            transform: Matrix::identity(4),
+            transform: Matrix::identity(4).into(),
             material: Material::default(),
+            ..Default::default()
         };
         let p5 = Pattern {
             kind: PatternKind::TestLocation,
             transform: scale(2.0, 2.0, 2.0),
+            jitter: None,
         };
         assert_eq!(
             p5.at_object(Float4::new_point(2.0, 3.0, 4.0), &s5),
@@ -165,12 +196,14 @@ mod test {
 
         let s6 = Object {
             shape: Shape::Sphere,
-            transform: scale(2.0, 2.0, 2.0),
+            transform: scale(2.0, 2.0, 2.0).into(),
             material: Material::default(),
+            ..Default::default()
         };
         let p6 = Pattern {
             kind: PatternKind::TestLocation,
             transform: translate(0.5, 1.0, 1.5),
+            jitter: None,
         };
         assert_eq!(
             p6.at_object(Float4::new_point(2.5, 3.0, 3.5), &s6),
@@ -183,6 +216,7 @@ mod test {
         let p = Pattern {
             kind: PatternKind::Gradient(Colour::white(), Colour::black()),
             transform: Matrix::identity(4),
+            jitter: None,
         };
         assert_eq!(p.at(Float4::origin()), Colour::white());
         assert_eq!(
@@ -204,6 +238,7 @@ mod test {
         let p = Pattern {
             kind: PatternKind::Ring(Colour::white(), Colour::black()),
             transform: Matrix::identity(4),
+            jitter: None,
         };
         assert_eq!(p.at(Float4::origin()), Colour::white());
         assert_eq!(p.at(Float4::new_point(1.0, 0.0, 0.0)), Colour::black());
@@ -211,11 +246,58 @@ mod test {
         assert_eq!(p.at(Float4::new_point(0.708, 0.0, 0.708)), Colour::black());
     }
 
+    #[test]
+    fn jitter() {
+        let p = Pattern {
+            kind: PatternKind::TestLocation,
+            transform: Matrix::identity(4),
+            jitter: Some(Noise::new(5, 1.0, 3)),
+        };
+        let point = Float4::new_point(0.3, 0.6, 0.9);
+        assert_eq!(p.at(point), Colour::new(0.3, 0.6, 0.9));
+
+        let p = Pattern {
+            jitter: Some(Noise::new(5, 1.0, 3).with_displacement(0.2)),
+            ..p
+        };
+        assert_ne!(p.at(point), Colour::new(0.3, 0.6, 0.9));
+    }
+
+    #[test]
+    fn noise_patterns() {
+        let noise = Noise::new(11, 1.0, 4);
+
+        let marble = Pattern {
+            kind: PatternKind::Marble(Colour::white(), Colour::black(), noise),
+            transform: Matrix::identity(4),
+            jitter: None,
+        };
+        assert_eq!(marble.at(Float4::origin()), Colour::new(0.5, 0.5, 0.5));
+
+        let wood = Pattern {
+            kind: PatternKind::Wood(Colour::white(), Colour::black(), noise),
+            transform: Matrix::identity(4),
+            jitter: None,
+        };
+        assert_eq!(wood.at(Float4::origin()), Colour::white());
+
+        let turbulence = Pattern {
+            kind: PatternKind::Turbulence(Colour::white(), Colour::black(), noise),
+            transform: Matrix::identity(4),
+            jitter: None,
+        };
+        assert_eq!(turbulence.at(Float4::origin()), Colour::white());
+        let c = turbulence.at(Float4::new_point(0.5, 0.25, 0.75));
+        assert_ne!(c, Colour::white());
+        assert!(c.0 .0[0] >= 0.0 && c.0 .0[0] <= 1.0);
+    }
+
     #[test]
     fn checkers() {
         let p = Pattern {
             kind: PatternKind::Checkers(Colour::white(), Colour::black()),
             transform: Matrix::identity(4),
+            jitter: None,
         };
 
         assert_eq!(p.at(Float4::origin()), Colour::white());
@@ -0,0 +1,381 @@
+use std::ops::Mul;
+
+use crate::{
+    float4::Float4,
+    matrix::{scale, translate, Matrix},
+    util::{float_is_eq, Float, EPSILON},
+    vector::Vector3,
+};
+
+// A rotation, kept as a unit quaternion. `q` and `-q` are the same rotation.
+#[derive(Debug, Clone, Copy)]
+pub struct Quaternion {
+    pub w: Float,
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
+}
+
+impl Quaternion {
+    pub const fn new(w: Float, x: Float, y: Float, z: Float) -> Self {
+        Self { w, x, y, z }
+    }
+
+    pub const fn identity() -> Self {
+        Self::new(1.0, 0.0, 0.0, 0.0)
+    }
+
+    // Right-handed about `axis`, which need not be normalised. A zero axis has
+    // no direction to turn about, and gives no rotation.
+    pub fn from_axis_angle(axis: Float4, angle: Float) -> Self {
+        if axis.mag() < EPSILON {
+            return Self::identity();
+        }
+        let axis = axis.normalise().scalar_mul((angle / 2.0).sin());
+        Self::new((angle / 2.0).cos(), axis.0[0], axis.0[1], axis.0[2])
+    }
+
+    // The same rotation as `rotate_z(z) * rotate_y(y) * rotate_x(x)`, that is x first.
+    pub fn from_euler(x: Float, y: Float, z: Float) -> Self {
+        let axis = |x, y, z| Float4::new_vector(x, y, z);
+        Self::from_axis_angle(axis(0.0, 0.0, 1.0), z)
+            * Self::from_axis_angle(axis(0.0, 1.0, 0.0), y)
+            * Self::from_axis_angle(axis(1.0, 0.0, 0.0), x)
+    }
+
+    // The rotation part of `m`, which must be orthonormal apart from rounding.
+    pub fn from_matrix(m: &Matrix) -> Self {
+        let m = &m.0;
+        let trace = m[0][0] + m[1][1] + m[2][2];
+        // pivot on the largest of w, x, y and z to keep the square root well away from zero
+        let q = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            Self::new(
+                s / 4.0,
+                (m[2][1] - m[1][2]) / s,
+                (m[0][2] - m[2][0]) / s,
+                (m[1][0] - m[0][1]) / s,
+            )
+        } else if m[0][0] > m[1][1] && m[0][0] > m[2][2] {
+            let s = (1.0 + m[0][0] - m[1][1] - m[2][2]).sqrt() * 2.0;
+            Self::new(
+                (m[2][1] - m[1][2]) / s,
+                s / 4.0,
+                (m[0][1] + m[1][0]) / s,
+                (m[0][2] + m[2][0]) / s,
+            )
+        } else if m[1][1] > m[2][2] {
+            let s = (1.0 + m[1][1] - m[0][0] - m[2][2]).sqrt() * 2.0;
+            Self::new(
+                (m[0][2] - m[2][0]) / s,
+                (m[0][1] + m[1][0]) / s,
+                s / 4.0,
+                (m[1][2] + m[2][1]) / s,
+            )
+        } else {
+            let s = (1.0 + m[2][2] - m[0][0] - m[1][1]).sqrt() * 2.0;
+            Self::new(
+                (m[1][0] - m[0][1]) / s,
+                (m[0][2] + m[2][0]) / s,
+                (m[1][2] + m[2][1]) / s,
+                s / 4.0,
+            )
+        };
+        q.normalise()
+    }
+
+    pub fn to_matrix(self) -> Matrix {
+        let Self { w, x, y, z } = self.normalise();
+        Matrix(vec![
+            vec![
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - w * z),
+                2.0 * (x * z + w * y),
+                0.0,
+            ],
+            vec![
+                2.0 * (x * y + w * z),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - w * x),
+                0.0,
+            ],
+            vec![
+                2.0 * (x * z - w * y),
+                2.0 * (y * z + w * x),
+                1.0 - 2.0 * (x * x + y * y),
+                0.0,
+            ],
+            vec![0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn dot(self, rhs: Self) -> Float {
+        self.w * rhs.w + self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
+    }
+
+    pub fn norm(self) -> Float {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalise(self) -> Self {
+        self.scaled(1.0 / self.norm())
+    }
+
+    pub fn conjugate(self) -> Self {
+        Self::new(self.w, -self.x, -self.y, -self.z)
+    }
+
+    pub fn rotate(self, v: Float4) -> Float4 {
+        let q = self.normalise();
+        let p = q * Self::new(0.0, v.0[0], v.0[1], v.0[2]) * q.conjugate();
+        Float4::new(p.x, p.y, p.z, v.0[3])
+    }
+
+    // Constant angular speed from `self` at t = 0 to `other` at t = 1, the short
+    // way round.
+    pub fn slerp(self, other: Self, t: Float) -> Self {
+        let (a, mut b) = (self.normalise(), other.normalise());
+        let mut cos = a.dot(b);
+        if cos < 0.0 {
+            b = b.scaled(-1.0);
+            cos = -cos;
+        }
+        // nearly parallel: the sine below vanishes, and a straight line is as good
+        if cos > 1.0 - EPSILON {
+            return a.scaled(1.0 - t).add(b.scaled(t)).normalise();
+        }
+
+        let angle = cos.acos();
+        let sin = angle.sin();
+        a.scaled(((1.0 - t) * angle).sin() / sin)
+            .add(b.scaled((t * angle).sin() / sin))
+    }
+
+    fn scaled(self, f: Float) -> Self {
+        Self::new(self.w * f, self.x * f, self.y * f, self.z * f)
+    }
+
+    fn add(self, rhs: Self) -> Self {
+        Self::new(
+            self.w + rhs.w,
+            self.x + rhs.x,
+            self.y + rhs.y,
+            self.z + rhs.z,
+        )
+    }
+}
+
+// Composition: `a * b` rotates by `b` first, as with matrices.
+impl Mul for Quaternion {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self::new(
+            self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+            self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+        )
+    }
+}
+
+// Equal when they describe the same rotation, so component by component up to
+// the sign of the whole.
+impl PartialEq for Quaternion {
+    fn eq(&self, other: &Self) -> bool {
+        let (a, b) = (self.normalise(), other.normalise());
+        let matches = |sign: Float| {
+            float_is_eq(a.w, sign * b.w)
+                && float_is_eq(a.x, sign * b.x)
+                && float_is_eq(a.y, sign * b.y)
+                && float_is_eq(a.z, sign * b.z)
+        };
+        matches(1.0) || matches(-1.0)
+    }
+}
+
+impl From<Quaternion> for Matrix {
+    fn from(value: Quaternion) -> Self {
+        value.to_matrix()
+    }
+}
+
+// An affine transform split into `translate * rotate * scale`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decomposition {
+    pub translation: Vector3,
+    pub rotation: Quaternion,
+    // a mirroring transform has its x scale negated
+    pub scale: Vector3,
+}
+
+impl Decomposition {
+    pub fn compose(&self) -> Matrix {
+        let (t, s) = (self.translation, self.scale);
+        translate(t.x, t.y, t.z) * self.rotation.to_matrix() * scale(s.x, s.y, s.z)
+    }
+
+    // Translation and scale move linearly; the rotation is slerped.
+    pub fn interpolate(&self, other: &Self, t: Float) -> Self {
+        let lerp = |a: Vector3, b: Vector3| a + (b - a) * t;
+        Self {
+            translation: lerp(self.translation, other.translation),
+            rotation: self.rotation.slerp(other.rotation, t),
+            scale: lerp(self.scale, other.scale),
+        }
+    }
+}
+
+impl Matrix {
+    // None unless this is a 4x4 affine transform with no zero scale and no
+    // shear, which has no place in the result.
+    pub fn decompose(&self) -> Option<Decomposition> {
+        let m = &self.0;
+        if m.len() != 4
+            || m.iter().any(|row| row.len() != 4)
+            || !(float_is_eq(m[3][0], 0.0)
+                && float_is_eq(m[3][1], 0.0)
+                && float_is_eq(m[3][2], 0.0)
+                && float_is_eq(m[3][3], 1.0))
+        {
+            return None;
+        }
+
+        // the columns are the scaled axes, which shear leaves out of square
+        let column = |c: usize| Float4::new_vector(m[0][c], m[1][c], m[2][c]);
+        let (x, y, z) = (column(0), column(1), column(2));
+        let (sx, sy, sz) = (x.mag(), y.mag(), z.mag());
+        if sx < EPSILON || sy < EPSILON || sz < EPSILON {
+            return None;
+        }
+        let (x, y, z) = (
+            x.scalar_mul(1.0 / sx),
+            y.scalar_mul(1.0 / sy),
+            z.scalar_mul(1.0 / sz),
+        );
+        if !(float_is_eq(x.dot(y), 0.0) && float_is_eq(x.dot(z), 0.0) && float_is_eq(y.dot(z), 0.0))
+        {
+            return None;
+        }
+
+        // a rotation keeps handedness, so a mirror goes into the scale
+        let (x, sx) = if x.cross(y).dot(z) < 0.0 {
+            (-x, -sx)
+        } else {
+            (x, sx)
+        };
+
+        let rotation = Matrix(vec![
+            vec![x.0[0], y.0[0], z.0[0], 0.0],
+            vec![x.0[1], y.0[1], z.0[1], 0.0],
+            vec![x.0[2], y.0[2], z.0[2], 0.0],
+            vec![0.0, 0.0, 0.0, 1.0],
+        ]);
+        Some(Decomposition {
+            translation: Vector3::new(m[0][3], m[1][3], m[2][3]),
+            rotation: Quaternion::from_matrix(&rotation),
+            scale: Vector3::new(sx, sy, sz),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        matrix::{rotate_x, rotate_y, rotate_z, shear},
+        util::consts::PI,
+    };
+
+    #[test]
+    fn axis_angle_and_euler() {
+        let x = Float4::new_vector(1.0, 0.0, 0.0);
+        let q = Quaternion::from_axis_angle(Float4::new_vector(0.0, 0.0, 2.0), PI / 2.0);
+        assert_eq!(q.rotate(x), Float4::new_vector(0.0, 1.0, 0.0));
+        assert_eq!(q.to_matrix(), rotate_z(PI / 2.0));
+
+        let (rx, ry, rz) = (0.3, -1.1, 2.5);
+        let q = Quaternion::from_euler(rx, ry, rz);
+        assert_eq!(q.to_matrix(), rotate_z(rz) * rotate_y(ry) * rotate_x(rx));
+
+        let p = Float4::new_point(1.0, 2.0, 3.0);
+        assert_eq!(q.rotate(p), q.to_matrix() * p);
+
+        let none = Quaternion::from_axis_angle(Float4::new_vector(0.0, 0.0, 0.0), 1.0);
+        assert_eq!(none, Quaternion::identity());
+        assert!(none.w.is_finite());
+    }
+
+    #[test]
+    fn matrix_round_trip() {
+        // one for each branch of `from_matrix`
+        for m in [
+            rotate_y(0.4) * rotate_x(0.2),
+            rotate_x(PI * 0.9),
+            rotate_y(PI * 0.9),
+            rotate_z(PI * 0.9),
+        ] {
+            assert_eq!(Quaternion::from_matrix(&m).to_matrix(), m);
+        }
+        // q and -q are the same rotation, but a small turn away is not
+        let q = Quaternion::new(0.5, 0.5, -0.5, 0.5);
+        assert_eq!(q, Quaternion::new(-0.5, -0.5, 0.5, -0.5));
+        let turned = q * Quaternion::from_axis_angle(Float4::new_vector(1.0, 0.0, 0.0), 0.01);
+        assert_ne!(q, turned);
+    }
+
+    #[test]
+    fn slerp() {
+        let axis = Float4::new_vector(0.0, 1.0, 0.0);
+        let a = Quaternion::identity();
+        let b = Quaternion::from_axis_angle(axis, PI / 2.0);
+        assert_eq!(a.slerp(b, 0.0), a);
+        assert_eq!(a.slerp(b, 1.0), b);
+        assert_eq!(a.slerp(b, 0.5), Quaternion::from_axis_angle(axis, PI / 4.0));
+        // the short way round, even when the signs point the long way
+        assert_eq!(
+            a.slerp(b.scaled(-1.0), 0.5),
+            Quaternion::from_axis_angle(axis, PI / 4.0)
+        );
+        assert!(float_is_eq(a.slerp(b, 0.3).norm(), 1.0));
+        assert_eq!(a.slerp(a, 0.7), a);
+    }
+
+    #[test]
+    fn decompose() {
+        let rotation = Quaternion::from_euler(0.5, -0.3, 1.2);
+        let m = translate(1.0, -2.0, 3.0) * rotation.to_matrix() * scale(2.0, 0.5, 3.0);
+        let d = m.decompose().unwrap();
+        assert_eq!(d.translation, Vector3::new(1.0, -2.0, 3.0));
+        assert_eq!(d.rotation, rotation);
+        assert_eq!(d.scale, Vector3::new(2.0, 0.5, 3.0));
+        assert_eq!(d.compose(), m);
+
+        let mirrored = rotate_y(0.7) * scale(1.0, -2.0, 1.0);
+        let d = mirrored.decompose().unwrap();
+        assert_eq!(d.compose(), mirrored);
+
+        // shear cannot be rebuilt from the parts, whether or not it is rotated
+        assert_eq!(shear(0.5, 0.0, 0.0, 0.0, 0.0, 0.0).decompose(), None);
+        let sheared = rotate_x(0.3) * shear(0.0, 0.0, 0.0, 0.0, 0.0, 0.1) * scale(2.0, 1.0, 1.0);
+        assert_eq!(sheared.decompose(), None);
+
+        assert_eq!(scale(1.0, 0.0, 1.0).decompose(), None);
+        assert_eq!(Matrix::identity(3).decompose(), None);
+    }
+
+    #[test]
+    fn interpolate() {
+        let a = Matrix::identity(4).decompose().unwrap();
+        let b = (translate(2.0, 0.0, 0.0) * rotate_z(PI / 2.0) * scale(3.0, 3.0, 3.0))
+            .decompose()
+            .unwrap();
+        let half = a.interpolate(&b, 0.5);
+        assert_eq!(half.translation, Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(half.scale, Vector3::new(2.0, 2.0, 2.0));
+        assert_eq!(
+            half.compose(),
+            translate(1.0, 0.0, 0.0) * rotate_z(PI / 4.0) * scale(2.0, 2.0, 2.0)
+        );
+    }
+}
@@ -1,130 +1,211 @@
+use std::cell::OnceCell;
+
 use crate::{
+    colour::Colour,
     float4::Float4,
     matrix::Matrix,
-    object::{Object, Shape},
-    util::EPSILON,
+    object::Object,
+    spectrum::Cauchy,
+    util::{Float, EPSILON},
+    vector::{Point3, Vector3},
 };
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Ray {
-    pub origin: Float4,
-    pub direction: Float4,
+    pub origin: Point3,
+    pub direction: Vector3,
+    // in micrometres; None for white light
+    pub wavelength: Option<Float>,
+    // one of several averaged over a rough surface, so any rough surface it
+    // goes on to reach takes a single sample
+    pub glossy_sample: bool,
 }
 
 impl Ray {
-    pub fn position(&self, t: f64) -> Float4 {
-        self.origin + self.direction.scalar_mul(t)
+    // a ray of white light
+    pub fn new(origin: Point3, direction: Vector3) -> Self {
+        Self {
+            origin,
+            direction,
+            wavelength: None,
+            glossy_sample: false,
+        }
+    }
+
+    pub fn position(&self, t: Float) -> Point3 {
+        self.origin + self.direction * t
     }
 
-    pub fn transform(&self, matrix: Matrix) -> Self {
-        let new_origin = matrix.multiply(&self.origin.into());
-        let new_direction = matrix.multiply(&self.direction.into());
+    pub fn transform(&self, matrix: &Matrix) -> Self {
         Self {
-            origin: new_origin.into(),
-            direction: new_direction.into(),
+            origin: matrix * self.origin,
+            direction: matrix * self.direction,
+            wavelength: self.wavelength,
+            glossy_sample: self.glossy_sample,
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct Intersection {
-    distance: f64,
+// Surface data at a hit. Most intersections along a ray are never shaded, so
+// this is only worked out when first asked for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Shading {
     point: Float4,
     eyev: Float4,
     normalv: Float4,
+    // without any bump, for deciding which side of the surface the ray is on
+    geometric_normalv: Float4,
     reflectv: Float4,
     inside: bool,
-    ray: Ray,
-    object: Object,
-
-    n1: Option<f64>,
-    n2: Option<f64>,
-}
-
-impl Default for Intersection {
-    fn default() -> Self {
-        Self {
-            distance: 0.0,
-            point: Float4::origin(),
-            eyev: Float4::new_vector(0.0, 0.0, 0.0),
-            normalv: Float4::new_vector(0.0, 0.0, 0.0),
-            reflectv: Float4::new_vector(0.0, 0.0, 0.0),
-            inside: false,
-            ray: Ray {
-                origin: Float4::origin(),
-                direction: Float4::new_vector(0.0, 0.0, 0.0),
-            },
-            object: Object {
-                shape: Shape::Sphere,
-                transform: Matrix::identity(4),
-                material: Default::default(),
-            },
-            n1: None,
-            n2: None,
-        }
-    }
 }
 
-impl Intersection {
-    pub fn new(ray: &Ray, object: &Object, distance: f64) -> Self {
-        let point = ray.position(distance);
-        let eyev = -ray.direction;
-        let mut normalv = object.normal_at(point);
-        let inside = normalv.dot(eyev) < 0.0;
+impl Shading {
+    fn new(ray: &Ray, object: &Object, distance: Float) -> Self {
+        let point = Float4::from(ray.position(distance));
+        let direction = Float4::from(ray.direction);
+        let eyev = -direction;
+        let (mut geometric_normalv, mut normalv) = object.normals_at(point);
+        let inside = geometric_normalv.dot(eyev) < 0.0;
         if inside {
+            geometric_normalv = -geometric_normalv;
             normalv = -normalv;
         }
-        let reflectv = ray.direction.reflect(normalv);
+        let reflectv = direction.reflect(normalv);
 
         Self {
-            distance,
             point,
             eyev,
             normalv,
+            geometric_normalv,
             reflectv,
             inside,
+        }
+    }
+}
+
+// The media either side of a hit, which depend on every intersection before it
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Boundary {
+    n1: Float,
+    n2: Float,
+    // absorption of the media on the n1 and n2 sides
+    absorption1: Colour,
+    absorption2: Colour,
+    dispersion1: Option<Cauchy>,
+    dispersion2: Option<Cauchy>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Intersection<'a> {
+    distance: Float,
+    ray: Ray,
+    object: &'a Object,
+    shading: OnceCell<Shading>,
+    // only known for intersections taken from `Intersections`
+    boundary: Option<Boundary>,
+}
+
+impl<'a> Intersection<'a> {
+    pub fn new(ray: &Ray, object: &'a Object, distance: Float) -> Self {
+        Self {
+            distance,
             ray: *ray,
-            object: object.clone(),
-            n1: None,
-            n2: None,
+            object,
+            shading: OnceCell::new(),
+            boundary: None,
         }
     }
 
-    pub fn distance(&self) -> f64 {
+    fn shading(&self) -> &Shading {
+        self.shading
+            .get_or_init(|| Shading::new(&self.ray, self.object, self.distance))
+    }
+
+    pub fn distance(&self) -> Float {
         self.distance
     }
     pub fn eyev(&self) -> Float4 {
-        self.eyev
+        self.shading().eyev
     }
     pub fn normalv(&self) -> Float4 {
-        self.normalv
+        self.shading().normalv
     }
     pub fn reflectv(&self) -> Float4 {
-        self.reflectv
+        self.shading().reflectv
     }
     pub fn inside(&self) -> bool {
-        self.inside
+        self.shading().inside
+    }
+    pub fn object(&self) -> &'a Object {
+        self.object
     }
-    pub fn object(&self) -> &Object {
-        &self.object
+    pub fn point(&self) -> Float4 {
+        self.shading().point
     }
 
+    // The offsets follow the real surface, as a bumped normal can point into it.
     pub fn over_point(&self) -> Float4 {
-        self.point + self.normalv.scalar_mul(EPSILON)
+        self.point()
+            + self
+                .shading()
+                .geometric_normalv
+                .scalar_mul(self.surface_offset())
     }
     pub fn under_point(&self) -> Float4 {
-        self.point - self.normalv.scalar_mul(EPSILON)
+        self.point()
+            - self
+                .shading()
+                .geometric_normalv
+                .scalar_mul(self.surface_offset())
+    }
+
+    // Far from the origin the rounding error in the hit point outgrows EPSILON,
+    // which only matters in single precision; in double precision it stays
+    // EPSILON out to millions of units.
+    fn surface_offset(&self) -> Float {
+        let point = self.point();
+        let magnitude = point.0[..3].iter().fold(0.0, |m: Float, c| m.max(c.abs()));
+        EPSILON.max(magnitude * Float::EPSILON * 16.0)
+    }
+
+    pub fn n1(&self) -> Float {
+        let boundary = self.boundary.unwrap();
+        match (self.ray.wavelength, boundary.dispersion1) {
+            (Some(wavelength), Some(cauchy)) => cauchy.at(wavelength),
+            _ => boundary.n1,
+        }
     }
-
-    pub fn n1(&self) -> f64 {
-        self.n1.unwrap()
+    pub fn n2(&self) -> Float {
+        let boundary = self.boundary.unwrap();
+        match (self.ray.wavelength, boundary.dispersion2) {
+            (Some(wavelength), Some(cauchy)) => cauchy.at(wavelength),
+            _ => boundary.n2,
+        }
+    }
+    pub fn is_dispersive(&self) -> bool {
+        self.boundary
+            .is_some_and(|b| b.dispersion1.is_some() || b.dispersion2.is_some())
+    }
+    pub fn wavelength(&self) -> Option<Float> {
+        self.ray.wavelength
+    }
+    pub fn is_glossy_sample(&self) -> bool {
+        self.ray.glossy_sample
+    }
+    pub fn with_wavelength(&self, wavelength: Float) -> Self {
+        let mut intersection = self.clone();
+        intersection.ray.wavelength = Some(wavelength);
+        intersection
     }
-    pub fn n2(&self) -> f64 {
-        self.n2.unwrap()
+    pub fn absorption1(&self) -> Colour {
+        self.boundary.map_or(Colour::black(), |b| b.absorption1)
+    }
+    pub fn absorption2(&self) -> Colour {
+        self.boundary.map_or(Colour::black(), |b| b.absorption2)
     }
 
-    pub fn schlick(&self) -> f64 {
-        let mut cos = self.eyev.dot(self.normalv);
+    pub fn schlick(&self) -> Float {
+        let mut cos = self.eyev().dot(self.normalv());
 
         if self.n1() > self.n2() {
             let n = self.n1() / self.n2();
@@ -140,20 +221,33 @@ impl Intersection {
         let r0 = ((self.n1() - self.n2()) / (self.n1() + self.n2())).powi(2);
         r0 + (1.0 - r0) * (1.0 - cos).powi(5)
     }
+
+    // None on total internal reflection
+    pub fn refractv(&self) -> Option<Float4> {
+        let (eyev, normalv) = (self.eyev(), self.normalv());
+        let n_ratio = self.n1() / self.n2();
+        let cos_i = eyev.dot(normalv);
+        let sin2_t = n_ratio.powi(2) * (1.0 - cos_i.powi(2));
+        if sin2_t > 1.0 {
+            return None;
+        }
+
+        let cos_t = (1.0 - sin2_t).sqrt();
+        Some(normalv.scalar_mul(n_ratio * cos_i - cos_t) - eyev.scalar_mul(n_ratio))
+    }
 }
 
+// Intersections along one ray, sorted by distance when they come from a world.
 #[derive(Debug, Clone)]
-pub struct Intersections(Vec<Intersection>);
+pub struct Intersections<'a>(Vec<Intersection<'a>>);
 
-impl Intersections {
-    pub fn new(is: Vec<Intersection>) -> Self {
-        let mut is = Self(is);
-        is.compute_refractive_indices();
-        is
+impl<'a> Intersections<'a> {
+    pub fn new(is: Vec<Intersection<'a>>) -> Self {
+        Self(is)
     }
 
     fn hit_index(&self) -> Option<usize> {
-        let mut min_pos_distance = f64::MAX;
+        let mut min_pos_distance = Float::MAX;
         let mut hi = None;
 
         for i in 0..self.0.len() {
@@ -166,51 +260,59 @@ impl Intersections {
         hi
     }
 
-    pub fn get_intersection_at(&self, index: usize) -> &Intersection {
-        &self.0[index]
+    // A copy of the intersection with the refractive indices either side of it
+    // filled in.
+    pub fn get_intersection_at(&self, index: usize) -> Intersection<'a> {
+        Intersection {
+            boundary: Some(self.boundary(index)),
+            ..self.0[index].clone()
+        }
     }
 
     pub fn count(&self) -> usize {
         self.0.len()
     }
 
-    pub fn into_inner(self) -> Vec<Intersection> {
-        self.0
+    pub fn iter(&self) -> impl Iterator<Item = &Intersection<'a>> {
+        self.0.iter()
     }
 
-    pub fn hit(&self) -> Option<Intersection> {
-        let hi = self.hit_index();
-        hi.map(|hi| self.0[hi].clone())
+    pub fn into_inner(self) -> Vec<Intersection<'a>> {
+        self.0
     }
 
-    fn compute_refractive_indices(&mut self) {
-        for hi in 0..self.count() {
-            let mut containers: Vec<&Object> = vec![];
-            for (i, ix) in self.0.iter_mut().enumerate() {
-                if i == hi {
-                    ix.n1 = Some(
-                        containers
-                            .last()
-                            .map_or(1.0, |o| o.material.refractive_index),
-                    );
-                }
+    pub fn hit(&self) -> Option<Intersection<'a>> {
+        self.hit_index().map(|hi| self.get_intersection_at(hi))
+    }
 
-                let cur_obj = &ix.object;
-                if let Some(pos) = containers.iter().position(|x| *x == cur_obj) {
+    fn boundary(&self, hi: usize) -> Boundary {
+        let mut containers: Vec<&Object> = vec![];
+        for ix in &self.0[..hi] {
+            match containers.iter().position(|x| std::ptr::eq(*x, ix.object)) {
+                Some(pos) => {
                     containers.remove(pos);
-                } else {
-                    containers.push(cur_obj);
                 }
+                None => containers.push(ix.object),
+            }
+        }
 
-                if i == hi {
-                    ix.n2 = Some(
-                        containers
-                            .last()
-                            .map_or(1.0, |o| o.material.refractive_index),
-                    );
-                    break;
-                }
+        let outer = containers.last().copied();
+        let hit_object = self.0[hi].object;
+        match containers.iter().position(|x| std::ptr::eq(*x, hit_object)) {
+            Some(pos) => {
+                containers.remove(pos);
             }
+            None => containers.push(hit_object),
+        }
+        let inner = containers.last().copied();
+
+        Boundary {
+            n1: outer.map_or(1.0, |o| o.material.refractive_index),
+            n2: inner.map_or(1.0, |o| o.material.refractive_index),
+            absorption1: outer.map_or(Colour::black(), |o| o.material.absorption),
+            absorption2: inner.map_or(Colour::black(), |o| o.material.absorption),
+            dispersion1: outer.and_then(|o| o.material.dispersion),
+            dispersion2: inner.and_then(|o| o.material.dispersion),
         }
     }
 }
@@ -218,32 +320,36 @@ impl Intersections {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::{matrix::*, object::Material, util::float_is_eq};
-    use std::f64::consts::PI;
+    use crate::{
+        bump::{Bump, UvMapping},
+        canvas::Canvas,
+        matrix::*,
+        noise::Noise,
+        object::{Material, Shape},
+        util::{consts::PI, float_is_eq},
+    };
 
     #[test]
     fn point_at_distance() {
-        let origin = Float4::new_point(2.0, 3.0, 4.0);
-        let direction = Float4::new_vector(1.0, 0.0, 0.0);
-        let ray = Ray { origin, direction };
+        let origin = Point3::new(2.0, 3.0, 4.0);
+        let direction = Vector3::new(1.0, 0.0, 0.0);
+        let ray = Ray::new(origin, direction);
 
         assert_eq!(ray.position(0.0), origin);
-        assert_eq!(ray.position(1.0), Float4::new_point(3.0, 3.0, 4.0));
-        assert_eq!(ray.position(-1.0), Float4::new_point(1.0, 3.0, 4.0));
-        assert_eq!(ray.position(2.5), Float4::new_point(4.5, 3.0, 4.0));
+        assert_eq!(ray.position(1.0), Point3::new(3.0, 3.0, 4.0));
+        assert_eq!(ray.position(-1.0), Point3::new(1.0, 3.0, 4.0));
+        assert_eq!(ray.position(2.5), Point3::new(4.5, 3.0, 4.0));
     }
 
     #[test]
     fn intersection_sphere() {
         let sphere1 = Object {
             shape: Shape::Sphere,
-            transform: Matrix::identity(4),
+            transform: Matrix::identity(4).into(),
             material: Material::default(),
+            ..Default::default()
         };
-        let ray = Ray {
-            origin: Float4::new_point(0.0, 1.0, -5.0),
-            direction: Float4::new_vector(0.0, 0.0, 1.0),
-        };
+        let ray = Ray::new(Point3::new(0.0, 1.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
         assert_eq!(
             sphere1
                 .intersect(&ray)
@@ -254,10 +360,7 @@ mod test {
             vec![5.0, 5.0]
         );
 
-        let ray = Ray {
-            origin: Float4::new_point(0.0, 2.0, -5.0),
-            direction: Float4::new_vector(0.0, 0.0, 1.0),
-        };
+        let ray = Ray::new(Point3::new(0.0, 2.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
         assert_eq!(
             sphere1
                 .intersect(&ray)
@@ -265,13 +368,10 @@ mod test {
                 .iter()
                 .map(|i| i.distance)
                 .collect::<Vec<_>>(),
-            vec![]
+            Vec::<Float>::new()
         );
 
-        let ray = Ray {
-            origin: Float4::origin(),
-            direction: Float4::new_vector(0.0, 0.0, 1.0),
-        };
+        let ray = Ray::new(Point3::origin(), Vector3::new(0.0, 0.0, 1.0));
         assert_eq!(
             sphere1
                 .intersect(&ray)
@@ -282,10 +382,7 @@ mod test {
             vec![-1.0, 1.0]
         );
 
-        let ray = Ray {
-            origin: Float4::new_point(0.0, 0.0, 5.0),
-            direction: Float4::new_vector(0.0, 0.0, 1.0),
-        };
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.0, 1.0));
         assert_eq!(
             sphere1
                 .intersect(&ray)
@@ -298,13 +395,11 @@ mod test {
 
         let sphere2 = Object {
             shape: Shape::Sphere,
-            transform: scale(2.0, 2.0, 2.0),
+            transform: scale(2.0, 2.0, 2.0).into(),
             material: Material::default(),
+            ..Default::default()
         };
-        let ray = Ray {
-            origin: Float4::new_point(0.0, 0.0, -5.0),
-            direction: Float4::new_vector(0.0, 0.0, 1.0),
-        };
+        let ray = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
         assert_eq!(
             sphere2
                 .intersect(&ray)
@@ -317,13 +412,11 @@ mod test {
 
         let sphere3 = Object {
             shape: Shape::Sphere,
-            transform: translate(5.0, 0.0, 0.0),
+            transform: translate(5.0, 0.0, 0.0).into(),
             material: Material::default(),
+            ..Default::default()
         };
-        let ray = Ray {
-            origin: Float4::new_point(0.0, 0.0, -5.0),
-            direction: Float4::new_vector(0.0, 0.0, 1.0),
-        };
+        let ray = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
         assert_eq!(
             sphere3
                 .intersect(&ray)
@@ -331,80 +424,54 @@ mod test {
                 .iter()
                 .map(|i| i.distance)
                 .collect::<Vec<_>>(),
-            vec![]
+            Vec::<Float>::new()
         )
     }
 
     #[test]
     fn hit_sphere() {
-        let i1 = Intersection {
-            distance: 5.0,
-            object: Object {
-                shape: Shape::Sphere,
-                transform: Matrix::identity(4),
-                material: Material::default(),
-            },
-            ..Default::default()
-        };
-        let i2 = Intersection {
-            distance: 7.0,
-            object: Object {
-                shape: Shape::Sphere,
-                transform: Matrix::identity(4),
-                material: Material::default(),
-            },
-            ..Default::default()
-        };
-        let i3 = Intersection {
-            distance: -3.0,
-            object: Object {
-                shape: Shape::Sphere,
-                transform: Matrix::identity(4),
-                material: Material::default(),
-            },
-            ..Default::default()
-        };
-        let i4 = Intersection {
-            distance: 2.0,
-            object: Object {
-                shape: Shape::Sphere,
-                transform: Matrix::identity(4),
-                material: Material::default(),
-            },
+        let sphere = Object {
+            shape: Shape::Sphere,
+            transform: Matrix::identity(4).into(),
+            material: Material::default(),
             ..Default::default()
         };
-        let intersections = Intersections(vec![i1.clone(), i2.clone(), i3.clone(), i4.clone()]);
-        assert_eq!(intersections.hit(), Some(i4));
+        let ray = Ray::new(Point3::origin(), Vector3::new(0.0, 0.0, 1.0));
+        let intersections = Intersections::new(
+            [5.0, 7.0, -3.0, 2.0]
+                .iter()
+                .map(|t| Intersection::new(&ray, &sphere, *t))
+                .collect(),
+        );
+        let hit = intersections.hit().unwrap();
+        assert_eq!(hit.distance(), 2.0);
+        assert_eq!(hit.object(), &sphere);
+        // the ray starts inside the sphere
+        assert_eq!((hit.n1(), hit.n2()), (1.0, 1.0));
+        assert!(hit.inside());
     }
 
     #[test]
     fn ray_transform() {
-        let r = Ray {
-            origin: Float4::new_point(1.0, 2.0, 3.0),
-            direction: Float4::new_vector(0.0, 1.0, 0.0),
-        };
+        let r = Ray::new(Point3::new(1.0, 2.0, 3.0), Vector3::new(0.0, 1.0, 0.0));
 
         let m1 = translate(3.0, 4.0, 5.0);
-        let expected1 = Ray {
-            origin: Float4::new_point(4.0, 6.0, 8.0),
-            direction: Float4::new_vector(0.0, 1.0, 0.0),
-        };
-        assert_eq!(r.transform(m1), expected1);
+        let expected1 = Ray::new(Point3::new(4.0, 6.0, 8.0), Vector3::new(0.0, 1.0, 0.0));
+        assert_eq!(r.transform(&m1), expected1);
 
         let m2 = scale(2.0, 3.0, 4.0);
-        let expected2 = Ray {
-            origin: Float4::new_point(2.0, 6.0, 12.0),
-            direction: Float4::new_vector(0.0, 3.0, 0.0),
-        };
-        assert_eq!(r.transform(m2), expected2);
+        let expected2 = Ray::new(Point3::new(2.0, 6.0, 12.0), Vector3::new(0.0, 3.0, 0.0));
+        assert_eq!(r.transform(&m2), expected2);
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn normal_at_sphere() {
         let sphere1 = Object {
             shape: Shape::Sphere,
-            transform: translate(0.0, 1.0, 0.0),
+            transform: translate(0.0, 1.0, 0.0).into(),
             material: Material::default(),
+            ..Default::default()
         };
         let normal = sphere1.normal_at(Float4::new_point(0.0, 1.70711, -0.70711));
         let expected = Float4::new_vector(0.0, 0.70711, -0.70711);
@@ -412,82 +479,139 @@ mod test {
 
         let sphere2 = Object {
             shape: Shape::Sphere,
-            transform: scale(1.0, 0.5, 1.0) * rotate_z(PI / 5.0),
+            transform: (scale(1.0, 0.5, 1.0) * rotate_z(PI / 5.0)).into(),
             material: Material::default(),
+            ..Default::default()
         };
         let normal2 = sphere2.normal_at(Float4::new_point(
             0.0,
-            1.0 / 2.0_f64.sqrt(),
-            -1.0 / 2.0_f64.sqrt(),
+            1.0 / Float::sqrt(2.0),
+            -1.0 / Float::sqrt(2.0),
         ));
         let expected2 = Float4::new_vector(0.0, 0.97014, -0.24254);
         assert_eq!(normal2, expected2);
     }
 
+    #[test]
+    fn normal_at_bump() {
+        let plane = Object {
+            shape: Shape::Plane,
+            transform: Matrix::identity(4).into(),
+            material: Material {
+                bump: Some(Bump::Noise(Noise::new(1, 0.5, 3))),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let point = Float4::new_point(0.3, 0.0, 0.7);
+        let normal = plane.normal_at(point);
+        assert!(float_is_eq(normal.mag(), 1.0));
+        assert_ne!(normal, Float4::new_vector(0.0, 1.0, 0.0));
+
+        let r = Ray::new(Point3::new(0.3, 1.0, 0.7), Vector3::new(0.0, -1.0, 0.0));
+        let i = Intersection::new(&r, &plane, 1.0);
+        assert_eq!(i.normalv(), normal);
+        assert_eq!(i.reflectv(), Float4::from(r.direction).reflect(normal));
+    }
+
+    #[test]
+    fn bumped_normal_below_surface() {
+        // the map tips the normal past the horizon, towards -x and down
+        let plane = Object {
+            shape: Shape::Plane,
+            transform: Matrix::identity(4).into(),
+            material: Material {
+                bump: Some(Bump::NormalMap {
+                    image: std::sync::Arc::new(Canvas::new(1, 1, Colour::new(0.0, 0.5, 0.3))),
+                    mapping: UvMapping::Planar,
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let r = Ray::new(Point3::new(0.5, 1.0, 0.5), Vector3::new(0.0, -1.0, 0.0));
+        let i = Intersection::new(&r, &plane, 1.0);
+        assert!(i.normalv().0[1] < 0.0);
+        assert!(!i.inside());
+        assert!(i.over_point().0[1] > 0.0);
+        assert!(i.under_point().0[1] < 0.0);
+    }
+
     #[test]
     fn intersection_in_out() {
         let sphere1 = Object {
             shape: Shape::Sphere,
-            transform: Matrix::identity(4),
+            transform: Matrix::identity(4).into(),
             material: Material::default(),
+            ..Default::default()
         };
-        let ray1 = Ray {
-            origin: Float4::new_point(0.0, 0.0, -5.0),
-            direction: Float4::new_vector(0.0, 0.0, 1.0),
-        };
+        let ray1 = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
         let distance1 = 4.0;
         let intersection1 = Intersection::new(&ray1, &sphere1, distance1);
-        assert!(!intersection1.inside);
+        assert!(!intersection1.inside());
 
         let sphere2 = Object {
             shape: Shape::Sphere,
-            transform: Matrix::identity(4),
+            transform: Matrix::identity(4).into(),
             material: Material::default(),
+            ..Default::default()
         };
-        let ray2 = Ray {
-            origin: Float4::origin(),
-            direction: Float4::new_vector(0.0, 0.0, 1.0),
-        };
+        let ray2 = Ray::new(Point3::origin(), Vector3::new(0.0, 0.0, 1.0));
         let distance2 = 1.0;
         let intersection2 = Intersection::new(&ray2, &sphere2, distance2);
-        assert!(intersection2.inside);
-        assert_eq!(intersection2.point, Float4::new_point(0.0, 0.0, 1.0));
-        assert_eq!(intersection2.eyev, Float4::new_vector(0.0, 0.0, -1.0));
-        assert_eq!(intersection2.normalv, Float4::new_vector(0.0, 0.0, -1.0));
+        assert!(intersection2.inside());
+        assert_eq!(intersection2.point(), Float4::new_point(0.0, 0.0, 1.0));
+        assert_eq!(intersection2.eyev(), Float4::new_vector(0.0, 0.0, -1.0));
+        assert_eq!(intersection2.normalv(), Float4::new_vector(0.0, 0.0, -1.0));
     }
 
     #[test]
     fn over_point() {
-        let r = Ray {
-            origin: Float4::new_point(0.0, 0.0, -5.0),
-            direction: Float4::new_vector(0.0, 0.0, 0.1),
-        };
+        let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 0.1));
 
         let s = Object {
             shape: Shape::Sphere,
-            transform: translate(0.0, 0.0, 1.0),
+            transform: translate(0.0, 0.0, 1.0).into(),
             material: Material::default(),
+            ..Default::default()
         };
         let i = Intersection::new(&r, &s, 5.0);
         assert!(i.over_point().0[2] < -EPSILON / 2.0);
-        assert!(i.point.0[2] > i.over_point().0[2]);
+        assert!(i.point().0[2] > i.over_point().0[2]);
+
+        // far from the origin the offset still clears the rounding in the point
+        let plane = Object {
+            shape: Shape::Plane,
+            transform: translate(0.0, 1e5, 0.0).into(),
+            material: Material::default(),
+            ..Default::default()
+        };
+        let r = Ray::new(
+            Point3::new(3e5, 1e5 + 1.0, 0.0),
+            Vector3::new(0.0, -1.0, 0.0),
+        );
+        let i = Intersection::new(&r, &plane, 1.0);
+        let gap = i.over_point().0[1] - 1e5;
+        assert!(gap >= EPSILON / 2.0);
+        assert!(gap > 3e5 * Float::EPSILON);
     }
 
     #[test]
     fn reflectv() {
         let o = Object {
             shape: Shape::Plane,
-            transform: Matrix::identity(4),
+            transform: Matrix::identity(4).into(),
             material: Material::default(),
+            ..Default::default()
         };
-        let r = Ray {
-            origin: Float4::new_point(0.0, 1.0, -1.0),
-            direction: Float4::new_vector(0.0, -1.0 / 2f64.sqrt(), 1.0 / 2f64.sqrt()),
-        };
-        let i = Intersection::new(&r, &o, 1.0 / 2f64.sqrt());
+        let r = Ray::new(
+            Point3::new(0.0, 1.0, -1.0),
+            Vector3::new(0.0, -1.0 / Float::sqrt(2.0), 1.0 / Float::sqrt(2.0)),
+        );
+        let i = Intersection::new(&r, &o, 1.0 / Float::sqrt(2.0));
         assert_eq!(
-            i.reflectv,
-            Float4::new_vector(0.0, 1.0 / 2f64.sqrt(), 1.0 / 2f64.sqrt())
+            i.reflectv(),
+            Float4::new_vector(0.0, 1.0 / Float::sqrt(2.0), 1.0 / Float::sqrt(2.0))
         );
     }
 
@@ -495,36 +619,37 @@ mod test {
     fn refractive_index() {
         let a = Object {
             shape: Shape::Sphere,
-            transform: scale(2.0, 2.0, 2.0),
+            transform: scale(2.0, 2.0, 2.0).into(),
             material: Material {
                 transparency: 1.0,
                 refractive_index: 1.5,
                 ..Default::default()
             },
+            ..Default::default()
         };
         let b = Object {
             shape: Shape::Sphere,
-            transform: translate(0.0, 0.0, -0.25),
+            transform: translate(0.0, 0.0, -0.25).into(),
             material: Material {
                 transparency: 1.0,
                 refractive_index: 2.0,
+                absorption: Colour::new(0.1, 0.2, 0.3),
                 ..Default::default()
             },
+            ..Default::default()
         };
         let c = Object {
             shape: Shape::Sphere,
-            transform: translate(0.0, 0.0, 0.25),
+            transform: translate(0.0, 0.0, 0.25).into(),
             material: Material {
                 transparency: 1.0,
                 refractive_index: 2.5,
                 ..Default::default()
             },
+            ..Default::default()
         };
 
-        let r = Ray {
-            origin: Float4::new_point(0.0, 0.0, -4.0),
-            direction: Float4::new_vector(0.0, 0.0, 1.0),
-        };
+        let r = Ray::new(Point3::new(0.0, 0.0, -4.0), Vector3::new(0.0, 0.0, 1.0));
 
         let intersections = Intersections::new(vec![
             Intersection::new(&r, &a, 2.0),
@@ -535,71 +660,141 @@ mod test {
             Intersection::new(&r, &a, 6.0),
         ]);
 
-        assert_eq!(intersections.get_intersection_at(0).n1, Some(1.0));
-        assert_eq!(intersections.get_intersection_at(0).n2, Some(1.5));
-        assert_eq!(intersections.get_intersection_at(1).n1, Some(1.5));
-        assert_eq!(intersections.get_intersection_at(1).n2, Some(2.0));
-        assert_eq!(intersections.get_intersection_at(2).n1, Some(2.0));
-        assert_eq!(intersections.get_intersection_at(2).n2, Some(2.5));
-        assert_eq!(intersections.get_intersection_at(3).n1, Some(2.5));
-        assert_eq!(intersections.get_intersection_at(3).n2, Some(2.5));
-        assert_eq!(intersections.get_intersection_at(4).n1, Some(2.5));
-        assert_eq!(intersections.get_intersection_at(4).n2, Some(1.5));
-        assert_eq!(intersections.get_intersection_at(5).n1, Some(1.5));
-        assert_eq!(intersections.get_intersection_at(5).n2, Some(1.0));
+        assert_eq!(intersections.get_intersection_at(0).n1(), 1.0);
+        assert_eq!(intersections.get_intersection_at(0).n2(), 1.5);
+        assert_eq!(intersections.get_intersection_at(1).n1(), 1.5);
+        assert_eq!(intersections.get_intersection_at(1).n2(), 2.0);
+        assert_eq!(intersections.get_intersection_at(2).n1(), 2.0);
+        assert_eq!(intersections.get_intersection_at(2).n2(), 2.5);
+        assert_eq!(intersections.get_intersection_at(3).n1(), 2.5);
+        assert_eq!(intersections.get_intersection_at(3).n2(), 2.5);
+        assert_eq!(intersections.get_intersection_at(4).n1(), 2.5);
+        assert_eq!(intersections.get_intersection_at(4).n2(), 1.5);
+        assert_eq!(intersections.get_intersection_at(5).n1(), 1.5);
+        assert_eq!(intersections.get_intersection_at(5).n2(), 1.0);
+
+        assert_eq!(
+            intersections.get_intersection_at(1).absorption2(),
+            Colour::new(0.1, 0.2, 0.3)
+        );
+        assert_eq!(
+            intersections.get_intersection_at(2).absorption1(),
+            Colour::new(0.1, 0.2, 0.3)
+        );
+        assert_eq!(
+            intersections.get_intersection_at(3).absorption2(),
+            Colour::black()
+        );
     }
 
     #[test]
-    fn under_point() {
-        let r = Ray {
-            origin: Float4::new_point(0.0, 0.0, -5.0),
-            direction: Float4::new_vector(0.0, 0.0, 1.0),
+    fn dispersive_refractive_index() {
+        let prism = Object {
+            shape: Shape::Sphere,
+            transform: Matrix::identity(4).into(),
+            material: Material {
+                transparency: 1.0,
+                refractive_index: 1.5,
+                dispersion: Some(Cauchy::BK7),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let is = Intersections::new(vec![
+            Intersection::new(&r, &prism, 4.0),
+            Intersection::new(&r, &prism, 6.0),
+        ]);
+        let entry = is.get_intersection_at(0);
+        assert!(entry.is_dispersive());
+        assert!(float_is_eq(entry.n1(), 1.0));
+        assert!(float_is_eq(entry.n2(), 1.5));
+
+        let blue = entry.with_wavelength(0.45);
+        let red = entry.with_wavelength(0.65);
+        assert!(float_is_eq(blue.n1(), 1.0));
+        assert!(float_is_eq(blue.n2(), Cauchy::BK7.at(0.45)));
+        assert!(blue.n2() > red.n2());
+
+        let exit = is.get_intersection_at(1).with_wavelength(0.45);
+        assert!(float_is_eq(exit.n1(), Cauchy::BK7.at(0.45)));
+        assert!(float_is_eq(exit.n2(), 1.0));
+    }
+
+    #[test]
+    fn refractive_index_equal_objects() {
+        // two glass spheres that compare equal are still two containers
+        let glass = Object {
+            material: Material {
+                transparency: 1.0,
+                refractive_index: 1.5,
+                ..Default::default()
+            },
+            ..Default::default()
         };
+        let (a, b) = (glass.clone(), glass);
+        let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let is = Intersections::new(vec![
+            Intersection::new(&r, &a, 4.0),
+            Intersection::new(&r, &b, 4.0),
+            Intersection::new(&r, &a, 6.0),
+            Intersection::new(&r, &b, 6.0),
+        ]);
+        let second = is.get_intersection_at(1);
+        assert!(float_is_eq(second.n1(), 1.5));
+        assert!(float_is_eq(second.n2(), 1.5));
+        let exit = is.get_intersection_at(3);
+        assert!(float_is_eq(exit.n1(), 1.5));
+        assert!(float_is_eq(exit.n2(), 1.0));
+    }
+
+    #[test]
+    fn under_point() {
+        let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
 
         let s = Object {
             shape: Shape::Sphere,
-            transform: translate(0.0, 0.0, 1.0),
+            transform: translate(0.0, 0.0, 1.0).into(),
             material: Material {
                 transparency: 1.0,
                 refractive_index: 1.5,
                 ..Default::default()
             },
+            ..Default::default()
         };
         let i = Intersection::new(&r, &s, 5.0);
 
         assert!(i.under_point().0[2] > EPSILON / 2.0);
-        assert!(i.point.0[2] < i.under_point().0[2]);
+        assert!(i.point().0[2] < i.under_point().0[2]);
     }
 
     #[test]
     fn schlick() {
         let s = Object {
             shape: Shape::Sphere,
-            transform: Matrix::identity(4),
+            transform: Matrix::identity(4).into(),
             material: Material {
                 transparency: 1.0,
                 refractive_index: 1.5,
                 ..Default::default()
             },
+            ..Default::default()
         };
 
-        let r1 = Ray {
-            origin: Float4::new_point(0.0, 0.0, 1.0 / 2f64.sqrt()),
-            direction: Float4::new_vector(0.0, 1.0, 0.0),
-        };
+        let r1 = Ray::new(
+            Point3::new(0.0, 0.0, 1.0 / Float::sqrt(2.0)),
+            Vector3::new(0.0, 1.0, 0.0),
+        );
         let intersections1 = Intersections::new(vec![
-            Intersection::new(&r1, &s, -1.0 / 2f64.sqrt()),
-            Intersection::new(&r1, &s, 1.0 / 2f64.sqrt()),
+            Intersection::new(&r1, &s, -1.0 / Float::sqrt(2.0)),
+            Intersection::new(&r1, &s, 1.0 / Float::sqrt(2.0)),
         ]);
         assert!(float_is_eq(
             intersections1.get_intersection_at(1).schlick(),
             1.0
         ));
 
-        let r2 = Ray {
-            origin: Float4::origin(),
-            direction: Float4::new_vector(0.0, 1.0, 0.0),
-        };
+        let r2 = Ray::new(Point3::origin(), Vector3::new(0.0, 1.0, 0.0));
 
         let intersections2 = Intersections::new(vec![
             Intersection::new(&r2, &s, -1.0),
@@ -610,10 +805,7 @@ mod test {
             0.04
         ));
 
-        let r3 = Ray {
-            origin: Float4::new_point(0.0, 0.99, -2.0),
-            direction: Float4::new_vector(0.0, 0.0, 1.0),
-        };
+        let r3 = Ray::new(Point3::new(0.0, 0.99, -2.0), Vector3::new(0.0, 0.0, 1.0));
 
         let intersections3 = Intersections::new(vec![Intersection::new(&r3, &s, 1.8589)]);
         assert!(float_is_eq(
@@ -0,0 +1,174 @@
+use crate::float4::Float4;
+use crate::util::{consts::PI, Float};
+
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    // deterministic per point, so repeated renders sample identically
+    // the bits are a u32 with the `f32` feature
+    #[allow(clippy::useless_conversion)]
+    pub fn from_point(point: Float4) -> Self {
+        Self::new(
+            point
+                .to_array()
+                .iter()
+                .fold(0, |h: u64, c| h.rotate_left(21) ^ u64::from(c.to_bits())),
+        )
+    }
+
+    // splitmix64
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // uniform in [0, 1)
+    pub fn next_float(&mut self) -> Float {
+        // exactly as many bits as the mantissa holds, so the result never rounds up to one
+        let bits = Float::MANTISSA_DIGITS;
+        (self.next_u64() >> (64 - bits)) as Float / (1u64 << bits) as Float
+    }
+}
+
+// Evenly spread points on the unit sphere, deterministic for a given count.
+pub fn fibonacci_sphere(count: usize) -> Vec<Float4> {
+    let golden_angle = PI * (3.0 - Float::sqrt(5.0));
+    (0..count)
+        .map(|i| {
+            let y = 1.0 - 2.0 * (i as Float + 0.5) / count as Float;
+            let r = (1.0 - y * y).sqrt();
+            let phi = golden_angle * i as Float;
+            Float4::new_point(phi.cos() * r, y, phi.sin() * r)
+        })
+        .collect()
+}
+
+pub fn in_unit_sphere(rng: &mut Rng) -> Float4 {
+    loop {
+        let v = Float4::new_vector(
+            2.0 * rng.next_float() - 1.0,
+            2.0 * rng.next_float() - 1.0,
+            2.0 * rng.next_float() - 1.0,
+        );
+        if v.dot(v) < 1.0 {
+            return v;
+        }
+    }
+}
+
+// A direction in a lobe around `direction` whose spread grows with `roughness`.
+// Falls back to `direction` if the sample crosses the surface given by `normal`.
+pub fn glossy_direction(
+    direction: Float4,
+    normal: Float4,
+    roughness: Float,
+    rng: &mut Rng,
+) -> Float4 {
+    if roughness <= 0.0 {
+        return direction;
+    }
+    let perturbed = (direction.normalise() + in_unit_sphere(rng).scalar_mul(roughness)).normalise();
+    if perturbed.dot(normal).signum() == direction.dot(normal).signum() {
+        perturbed
+    } else {
+        direction
+    }
+}
+
+pub fn orthonormal_basis(normal: Float4) -> (Float4, Float4) {
+    let helper = if normal.0[0].abs() > 0.9 {
+        Float4::new_vector(0.0, 1.0, 0.0)
+    } else {
+        Float4::new_vector(1.0, 0.0, 0.0)
+    };
+    let tangent = helper.cross(normal).normalise();
+    let bitangent = normal.cross(tangent);
+    (tangent, bitangent)
+}
+
+pub fn cosine_hemisphere(normal: Float4, rng: &mut Rng) -> Float4 {
+    let (tangent, bitangent) = orthonormal_basis(normal);
+    let r = rng.next_float().sqrt();
+    let phi = 2.0 * PI * rng.next_float();
+    let z = (1.0 - r * r).max(0.0).sqrt();
+
+    (tangent.scalar_mul(r * phi.cos()) + bitangent.scalar_mul(r * phi.sin()) + normal.scalar_mul(z))
+        .normalise()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::{float_is_eq, EPSILON};
+
+    #[test]
+    fn rng_range() {
+        let mut rng = Rng::new(3);
+        for _ in 0..1000 {
+            let f = rng.next_float();
+            assert!((0.0..1.0).contains(&f));
+        }
+        assert_eq!(Rng::new(5).next_u64(), Rng::new(5).next_u64());
+    }
+
+    #[test]
+    fn fibonacci_sphere_points() {
+        let points = fibonacci_sphere(64);
+        assert_eq!(points.len(), 64);
+        let centroid = points
+            .iter()
+            .fold(Float4::new_vector(0.0, 0.0, 0.0), |acc, p| {
+                acc + (*p - Float4::origin())
+            });
+        for p in points {
+            assert!(p.is_point());
+            assert!(float_is_eq((p - Float4::origin()).mag(), 1.0));
+        }
+        assert!(centroid.mag() / 64.0 < 0.01);
+    }
+
+    #[test]
+    fn glossy_directions() {
+        let mut rng = Rng::new(23);
+        let normal = Float4::new_vector(0.0, 1.0, 0.0);
+        let ideal = Float4::new_vector(1.0, 1.0, 0.0).normalise();
+        assert_eq!(glossy_direction(ideal, normal, 0.0, &mut rng), ideal);
+
+        for _ in 0..1000 {
+            let d = glossy_direction(ideal, normal, 0.3, &mut rng);
+            assert!(float_is_eq(d.mag(), 1.0));
+            assert!(d.dot(normal) > 0.0);
+            // a unit offset of at most 0.3 tilts by less than asin(0.3)
+            assert!(d.dot(ideal) > Float::asin(0.3).cos() - EPSILON);
+        }
+
+        let refracted = Float4::new_vector(0.0, -1.0, 0.0);
+        for _ in 0..100 {
+            assert!(glossy_direction(refracted, normal, 0.5, &mut rng).dot(normal) < 0.0);
+        }
+    }
+
+    #[test]
+    fn cosine_hemisphere_samples() {
+        let mut rng = Rng::new(17);
+        let normal = Float4::new_vector(0.0, 0.0, 1.0);
+        let n = 20000;
+        let mut mean_cos = 0.0;
+        for _ in 0..n {
+            let d = cosine_hemisphere(normal, &mut rng);
+            assert!(float_is_eq(d.mag(), 1.0));
+            assert!(d.dot(normal) >= 0.0);
+            mean_cos += d.dot(normal);
+        }
+        // E[cos] = 2/3 for a cosine-weighted hemisphere
+        assert!((mean_cos / n as Float - 2.0 / 3.0).abs() < 0.01);
+    }
+}
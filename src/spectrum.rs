@@ -0,0 +1,87 @@
+use crate::colour::Colour;
+use crate::util::Float;
+
+pub const WAVELENGTH_SAMPLES: usize = 8;
+
+const VISIBLE_MIN: Float = 0.40;
+const VISIBLE_MAX: Float = 0.70;
+
+// Cauchy's equation, n(λ) = a + b / λ², with λ in micrometres.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cauchy {
+    pub a: Float,
+    pub b: Float,
+}
+
+impl Cauchy {
+    pub const BK7: Self = Self {
+        a: 1.5046,
+        b: 0.00420,
+    };
+    pub const DIAMOND: Self = Self {
+        a: 2.3818,
+        b: 0.01210,
+    };
+
+    pub fn at(&self, wavelength: Float) -> Float {
+        self.a + self.b / wavelength.powi(2)
+    }
+}
+
+fn gaussian(x: Float, centre: Float, width: Float) -> Float {
+    (-((x - centre) / width).powi(2) / 2.0).exp()
+}
+
+fn response(wavelength: Float) -> Colour {
+    Colour::new(
+        gaussian(wavelength, 0.61, 0.05) + 0.2 * gaussian(wavelength, 0.42, 0.02),
+        gaussian(wavelength, 0.54, 0.04),
+        gaussian(wavelength, 0.45, 0.03),
+    )
+}
+
+// Evenly spaced wavelengths across the visible range, each paired with the
+// weight it contributes to RGB. The weights of every channel sum to one, so a
+// non-dispersive path recombines to its original colour.
+pub fn wavelength_samples(count: usize) -> Vec<(Float, Colour)> {
+    let wavelengths = (0..count)
+        .map(|i| VISIBLE_MIN + (VISIBLE_MAX - VISIBLE_MIN) * (i as Float + 0.5) / count as Float)
+        .collect::<Vec<_>>();
+    let total = wavelengths
+        .iter()
+        .fold(Colour::black(), |acc, w| acc + response(*w));
+    let (r, g, b) = (total.0 .0[0], total.0 .0[1], total.0 .0[2]);
+
+    wavelengths
+        .into_iter()
+        .map(|w| {
+            let c = response(w).0 .0;
+            (w, Colour::new(c[0] / r, c[1] / g, c[2] / b))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::float_is_eq;
+
+    #[test]
+    fn cauchy() {
+        assert!(float_is_eq(Cauchy::BK7.at(0.5893), 1.51669));
+        assert!(Cauchy::BK7.at(0.4) > Cauchy::BK7.at(0.7));
+    }
+
+    #[test]
+    fn samples_recombine_to_white() {
+        let samples = wavelength_samples(WAVELENGTH_SAMPLES);
+        assert_eq!(samples.len(), WAVELENGTH_SAMPLES);
+        let total = samples
+            .iter()
+            .fold(Colour::black(), |acc, (_, weight)| acc + *weight);
+        assert_eq!(total, Colour::white());
+        assert!(samples
+            .iter()
+            .all(|(w, _)| (VISIBLE_MIN..=VISIBLE_MAX).contains(w)));
+    }
+}
@@ -0,0 +1,205 @@
+use std::cell::Cell;
+use std::fmt;
+use std::ops::{Add, Sub};
+use std::time::Duration;
+
+// Running totals for whatever the current thread has traced while counting. Every
+// pixel is rendered on a single thread, so the difference between two snapshots
+// taken around it counts exactly that pixel's work.
+thread_local! {
+    static COUNTS: Cell<RayCounts> = Cell::new(RayCounts::default());
+    static COUNTING: Cell<bool> = const { Cell::new(false) };
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RayCounts {
+    pub primary: u64,
+    // towards the point light or an emitter
+    pub shadow: u64,
+    // hemisphere samples of the environment
+    pub environment: u64,
+    // mirror and glossy bounces
+    pub reflection: u64,
+    pub refraction: u64,
+    // the path tracer's bounces off diffuse surfaces
+    pub diffuse: u64,
+    pub sphere_tests: u64,
+    pub plane_tests: u64,
+    // sum of the recursion depth at every shaded hit
+    pub depth_total: u64,
+    pub shaded: u64,
+    // secondary rays that were not traced because the recursion limit was reached
+    pub truncated: u64,
+}
+
+impl RayCounts {
+    pub fn intersection_tests(&self) -> u64 {
+        self.sphere_tests + self.plane_tests
+    }
+
+    pub fn average_depth(&self) -> f64 {
+        if self.shaded == 0 {
+            0.0
+        } else {
+            self.depth_total as f64 / self.shaded as f64
+        }
+    }
+
+    pub fn truncated_share(&self) -> f64 {
+        let requested = self.reflection + self.refraction + self.diffuse + self.truncated;
+        if requested == 0 {
+            0.0
+        } else {
+            self.truncated as f64 / requested as f64
+        }
+    }
+}
+
+impl Add for RayCounts {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self {
+            primary: self.primary + rhs.primary,
+            shadow: self.shadow + rhs.shadow,
+            environment: self.environment + rhs.environment,
+            reflection: self.reflection + rhs.reflection,
+            refraction: self.refraction + rhs.refraction,
+            diffuse: self.diffuse + rhs.diffuse,
+            sphere_tests: self.sphere_tests + rhs.sphere_tests,
+            plane_tests: self.plane_tests + rhs.plane_tests,
+            depth_total: self.depth_total + rhs.depth_total,
+            shaded: self.shaded + rhs.shaded,
+            truncated: self.truncated + rhs.truncated,
+        }
+    }
+}
+
+impl Sub for RayCounts {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self {
+            primary: self.primary - rhs.primary,
+            shadow: self.shadow - rhs.shadow,
+            environment: self.environment - rhs.environment,
+            reflection: self.reflection - rhs.reflection,
+            refraction: self.refraction - rhs.refraction,
+            diffuse: self.diffuse - rhs.diffuse,
+            sphere_tests: self.sphere_tests - rhs.sphere_tests,
+            plane_tests: self.plane_tests - rhs.plane_tests,
+            depth_total: self.depth_total - rhs.depth_total,
+            shaded: self.shaded - rhs.shaded,
+            truncated: self.truncated - rhs.truncated,
+        }
+    }
+}
+
+// this thread's totals so far
+pub fn snapshot() -> RayCounts {
+    COUNTS.with(Cell::get)
+}
+
+// Nothing is counted unless a `Counting` is alive on this thread, so renders
+// that do not ask for statistics do not pay for them.
+pub struct Counting {
+    was_counting: bool,
+}
+
+pub fn start_counting() -> Counting {
+    Counting {
+        was_counting: COUNTING.replace(true),
+    }
+}
+
+impl Drop for Counting {
+    fn drop(&mut self) {
+        COUNTING.set(self.was_counting);
+    }
+}
+
+pub(crate) fn record(f: impl FnOnce(&mut RayCounts)) {
+    if !COUNTING.get() {
+        return;
+    }
+    COUNTS.with(|counts| {
+        let mut c = counts.get();
+        f(&mut c);
+        counts.set(c);
+    });
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenderStats {
+    pub counts: RayCounts,
+    pub tile_times: Vec<Duration>,
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for RenderStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let c = &self.counts;
+        writeln!(f, "render time        {:.3?}", self.elapsed)?;
+        writeln!(f, "primary rays       {}", c.primary)?;
+        writeln!(f, "shadow rays        {}", c.shadow)?;
+        writeln!(f, "environment rays   {}", c.environment)?;
+        writeln!(f, "reflection rays    {}", c.reflection)?;
+        writeln!(f, "refraction rays    {}", c.refraction)?;
+        writeln!(f, "diffuse rays       {}", c.diffuse)?;
+        writeln!(
+            f,
+            "intersection tests {} (sphere {}, plane {})",
+            c.intersection_tests(),
+            c.sphere_tests,
+            c.plane_tests
+        )?;
+        writeln!(f, "average depth      {:.3}", c.average_depth())?;
+        writeln!(f, "recursion limited  {:.2}%", c.truncated_share() * 100.0)?;
+        if let (Some(min), Some(max)) = (self.tile_times.iter().min(), self.tile_times.iter().max())
+        {
+            let mean = self.tile_times.iter().sum::<Duration>() / self.tile_times.len() as u32;
+            write!(
+                f,
+                "tile time          {:.3?} min, {:.3?} mean, {:.3?} max over {} tiles",
+                min,
+                mean,
+                max,
+                self.tile_times.len()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn record_and_snapshot() {
+        let before = snapshot();
+        record(|c| c.primary += 1);
+        assert_eq!(snapshot(), before);
+
+        let counting = start_counting();
+        record(|c| {
+            c.primary += 1;
+            c.reflection += 3;
+            c.truncated += 1;
+            c.depth_total += 3;
+            c.shaded += 2;
+        });
+        let counts = snapshot() - before;
+        assert_eq!(counts.primary, 1);
+        assert_eq!(counts.average_depth(), 1.5);
+        assert_eq!(counts.truncated_share(), 0.25);
+        assert_eq!((counts + counts).reflection, 6);
+        drop(counting);
+        record(|c| c.primary += 1);
+        assert_eq!(snapshot() - before, counts);
+
+        // other threads keep their own totals
+        let elsewhere = std::thread::spawn(snapshot).join().unwrap();
+        assert_eq!(elsewhere, RayCounts::default());
+    }
+}
@@ -0,0 +1,115 @@
+use crate::colour::Colour;
+use crate::util::Float;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Curve {
+    // leaves values as they are; anything above one clips when written
+    Clamp,
+    Reinhard,
+    // Narkowicz's fit of the ACES filmic curve
+    Aces,
+}
+
+impl Curve {
+    pub fn apply(&self, x: Float) -> Float {
+        let x = x.max(0.0);
+        match self {
+            Curve::Clamp => x,
+            Curve::Reinhard => x / (1.0 + x),
+            Curve::Aces => {
+                ((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)).clamp(0.0, 1.0)
+            }
+        }
+    }
+}
+
+// How linear radiance is squeezed into [0, 1] for 8-bit formats.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ToneMap {
+    // in stops; each one doubles the brightness
+    pub exposure: Float,
+    pub curve: Curve,
+    pub srgb: bool,
+}
+
+impl Default for ToneMap {
+    fn default() -> Self {
+        Self {
+            exposure: 0.0,
+            curve: Curve::Clamp,
+            srgb: false,
+        }
+    }
+}
+
+impl ToneMap {
+    pub fn apply(&self, colour: Colour) -> Colour {
+        let scale = Float::powf(2.0, self.exposure);
+        let channel = |c: Float| {
+            let c = self.curve.apply(c * scale);
+            if self.srgb {
+                srgb_encode(c)
+            } else {
+                c
+            }
+        };
+        let c = colour.0 .0;
+        Colour::new(channel(c[0]), channel(c[1]), channel(c[2]))
+    }
+}
+
+pub fn srgb_encode(linear: Float) -> Float {
+    if linear <= 0.0031308 {
+        12.92 * linear
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::float_is_eq;
+
+    #[test]
+    fn curves() {
+        assert!(float_is_eq(Curve::Clamp.apply(3.0), 3.0));
+        assert!(float_is_eq(Curve::Reinhard.apply(1.0), 0.5));
+        assert!(float_is_eq(Curve::Aces.apply(0.0), 0.0));
+        assert!(float_is_eq(Curve::Aces.apply(100.0), 1.0));
+        for curve in [Curve::Reinhard, Curve::Aces] {
+            assert!(curve.apply(0.5) < curve.apply(2.0));
+            assert!(curve.apply(1e6) <= 1.0);
+        }
+    }
+
+    #[test]
+    fn tone_map() {
+        let colour = Colour::new(0.25, 1.0, 4.0);
+        assert_eq!(ToneMap::default().apply(colour), colour);
+
+        let brighter = ToneMap {
+            exposure: 1.0,
+            ..Default::default()
+        };
+        assert_eq!(brighter.apply(colour), Colour::new(0.5, 2.0, 8.0));
+
+        let display = ToneMap {
+            curve: Curve::Reinhard,
+            srgb: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            display.apply(colour),
+            Colour::new(srgb_encode(0.2), srgb_encode(0.5), srgb_encode(0.8))
+        );
+    }
+
+    #[test]
+    fn srgb() {
+        assert!(float_is_eq(srgb_encode(0.0), 0.0));
+        assert!(float_is_eq(srgb_encode(1.0), 1.0));
+        assert!(float_is_eq(srgb_encode(0.001), 0.01292));
+        assert!(float_is_eq(srgb_encode(0.5), 0.73536));
+    }
+}
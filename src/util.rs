@@ -1,5 +1,23 @@
-pub const EPSILON: f64 = 1e-5;
+// The precision all geometry and colour maths is done in. Building with the
+// `f32` feature halves the memory of every vector, matrix and colour at the
+// cost of accuracy.
+#[cfg(not(feature = "f32"))]
+pub type Float = f64;
+#[cfg(feature = "f32")]
+pub type Float = f32;
 
-pub fn float_is_eq(a: f64, b: f64) -> bool {
+#[cfg(feature = "f32")]
+pub use std::f32::consts;
+#[cfg(not(feature = "f32"))]
+pub use std::f64::consts;
+
+#[cfg(not(feature = "f32"))]
+pub const EPSILON: Float = 1e-5;
+// rounding alone is around 1e-7 of a value, and the matrix inverse compounds it;
+// see also `Intersection::surface_offset`
+#[cfg(feature = "f32")]
+pub const EPSILON: Float = 1e-4;
+
+pub fn float_is_eq(a: Float, b: Float) -> bool {
     (a - b).abs() < EPSILON
 }
@@ -0,0 +1,362 @@
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+use crate::{
+    float4::Float4,
+    matrix::Matrix,
+    util::{float_is_eq, Float},
+};
+
+// Points and vectors as separate types, so that only the operations that make
+// sense between them compile: point - point is a vector, point + vector is a
+// point, and points cannot be added, scaled or normalised. Rays are made of
+// them; both convert to and from `Float4` where a ray meets the rest of the
+// crate, which has yet to move over.
+
+#[derive(Debug, Clone, Copy)]
+pub struct Point3 {
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Vector3 {
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
+}
+
+impl Point3 {
+    pub const fn new(x: Float, y: Float, z: Float) -> Self {
+        Self { x, y, z }
+    }
+
+    pub const fn origin() -> Self {
+        Self::new(0.0, 0.0, 0.0)
+    }
+
+    pub fn distance(self, other: Self) -> Float {
+        (self - other).mag()
+    }
+
+    // the vector from the origin to this point
+    pub fn to_vector(self) -> Vector3 {
+        Vector3::new(self.x, self.y, self.z)
+    }
+}
+
+impl Vector3 {
+    pub const fn new(x: Float, y: Float, z: Float) -> Self {
+        Self { x, y, z }
+    }
+
+    pub const fn zero() -> Self {
+        Self::new(0.0, 0.0, 0.0)
+    }
+
+    pub fn mag(self) -> Float {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalise(self) -> Self {
+        self / self.mag()
+    }
+
+    pub fn dot(self, rhs: Self) -> Float {
+        self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
+    }
+
+    pub fn cross(self, rhs: Self) -> Self {
+        Self::new(
+            self.y * rhs.z - self.z * rhs.y,
+            self.z * rhs.x - self.x * rhs.z,
+            self.x * rhs.y - self.y * rhs.x,
+        )
+    }
+
+    pub fn reflect(self, normal: Self) -> Self {
+        self - normal * (2.0 * self.dot(normal))
+    }
+}
+
+impl Sub for Point3 {
+    type Output = Vector3;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Vector3::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
+    }
+}
+
+impl Add<Vector3> for Point3 {
+    type Output = Self;
+
+    fn add(self, rhs: Vector3) -> Self::Output {
+        Self::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
+    }
+}
+
+impl Sub<Vector3> for Point3 {
+    type Output = Self;
+
+    fn sub(self, rhs: Vector3) -> Self::Output {
+        self + -rhs
+    }
+}
+
+impl AddAssign<Vector3> for Point3 {
+    fn add_assign(&mut self, rhs: Vector3) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign<Vector3> for Point3 {
+    fn sub_assign(&mut self, rhs: Vector3) {
+        *self = *self - rhs;
+    }
+}
+
+impl Add for Vector3 {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
+    }
+}
+
+impl Add<Point3> for Vector3 {
+    type Output = Point3;
+
+    fn add(self, rhs: Point3) -> Self::Output {
+        rhs + self
+    }
+}
+
+impl Sub for Vector3 {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self + -rhs
+    }
+}
+
+impl AddAssign for Vector3 {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Vector3 {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl Neg for Vector3 {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        self * -1.0
+    }
+}
+
+impl Mul<Float> for Vector3 {
+    type Output = Self;
+
+    fn mul(self, rhs: Float) -> Self::Output {
+        Self::new(self.x * rhs, self.y * rhs, self.z * rhs)
+    }
+}
+
+impl Mul<Vector3> for Float {
+    type Output = Vector3;
+
+    fn mul(self, rhs: Vector3) -> Self::Output {
+        rhs * self
+    }
+}
+
+impl Div<Float> for Vector3 {
+    type Output = Self;
+
+    fn div(self, rhs: Float) -> Self::Output {
+        Self::new(self.x / rhs, self.y / rhs, self.z / rhs)
+    }
+}
+
+impl PartialEq for Point3 {
+    fn eq(&self, other: &Self) -> bool {
+        float_is_eq(self.x, other.x) && float_is_eq(self.y, other.y) && float_is_eq(self.z, other.z)
+    }
+}
+
+impl PartialEq for Vector3 {
+    fn eq(&self, other: &Self) -> bool {
+        float_is_eq(self.x, other.x) && float_is_eq(self.y, other.y) && float_is_eq(self.z, other.z)
+    }
+}
+
+// Points pick up the translation part of a transform, vectors do not. Only
+// affine transforms keep a point a point.
+impl Mul<Point3> for &Matrix {
+    type Output = Point3;
+
+    fn mul(self, rhs: Point3) -> Self::Output {
+        let p = self * Float4::from(rhs);
+        assert!(float_is_eq(p.0[3], 1.0), "not an affine transform");
+        Point3::new(p.0[0], p.0[1], p.0[2])
+    }
+}
+
+impl Mul<Point3> for Matrix {
+    type Output = Point3;
+
+    fn mul(self, rhs: Point3) -> Self::Output {
+        &self * rhs
+    }
+}
+
+impl Mul<Vector3> for &Matrix {
+    type Output = Vector3;
+
+    fn mul(self, rhs: Vector3) -> Self::Output {
+        let v = self * Float4::from(rhs);
+        Vector3::new(v.0[0], v.0[1], v.0[2])
+    }
+}
+
+impl Mul<Vector3> for Matrix {
+    type Output = Vector3;
+
+    fn mul(self, rhs: Vector3) -> Self::Output {
+        &self * rhs
+    }
+}
+
+impl Matrix {
+    // Normals need the inverse transpose to stay perpendicular to the surface
+    // under non-uniform scaling. `self` is the inverse of the object transform.
+    pub fn transform_normal(&self, normal: Vector3) -> Vector3 {
+        (self.transpose() * normal).normalise()
+    }
+}
+
+impl From<Point3> for Float4 {
+    fn from(value: Point3) -> Self {
+        Float4::new_point(value.x, value.y, value.z)
+    }
+}
+
+impl From<Vector3> for Float4 {
+    fn from(value: Vector3) -> Self {
+        Float4::new_vector(value.x, value.y, value.z)
+    }
+}
+
+impl Float4 {
+    // For values that are known to be points, such as a hit position
+    pub fn to_point3(self) -> Point3 {
+        Point3::try_from(self).expect("not a point")
+    }
+
+    // For values that are known to be vectors, such as a sampled direction
+    pub fn to_vector3(self) -> Vector3 {
+        Vector3::try_from(self).expect("not a vector")
+    }
+}
+
+// Fails with the original value unless `w` is one.
+impl TryFrom<Float4> for Point3 {
+    type Error = Float4;
+
+    fn try_from(value: Float4) -> Result<Self, Self::Error> {
+        if value.is_point() {
+            Ok(Self::new(value.0[0], value.0[1], value.0[2]))
+        } else {
+            Err(value)
+        }
+    }
+}
+
+// Fails with the original value unless `w` is zero.
+impl TryFrom<Float4> for Vector3 {
+    type Error = Float4;
+
+    fn try_from(value: Float4) -> Result<Self, Self::Error> {
+        if value.is_vector() {
+            Ok(Self::new(value.0[0], value.0[1], value.0[2]))
+        } else {
+            Err(value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::matrix::{rotate_z, scale, translate};
+    use crate::util::consts::PI;
+
+    #[test]
+    fn arithmetic() {
+        let a = Point3::new(3.0, 2.0, 1.0);
+        let b = Point3::new(5.0, 6.0, 7.0);
+        let v = Vector3::new(1.0, 0.0, -1.0);
+        assert_eq!(a - b, Vector3::new(-2.0, -4.0, -6.0));
+        assert_eq!(a + v, Point3::new(4.0, 2.0, 0.0));
+        assert_eq!(v + a, a + v);
+        assert_eq!(a - v, Point3::new(2.0, 2.0, 2.0));
+        assert_eq!(-v * 2.0, Vector3::new(-2.0, 0.0, 2.0));
+        assert_eq!(a.distance(b), Float::sqrt(56.0));
+
+        let x = Vector3::new(1.0, 0.0, 0.0);
+        let y = Vector3::new(0.0, 1.0, 0.0);
+        assert_eq!(x.cross(y), Vector3::new(0.0, 0.0, 1.0));
+        assert_eq!(x.dot(y), 0.0);
+        assert_eq!(Vector3::new(0.0, 3.0, 4.0).normalise().mag(), 1.0);
+        assert_eq!(
+            Vector3::new(1.0, -1.0, 0.0).reflect(y),
+            Vector3::new(1.0, 1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn transforms() {
+        let m = translate(1.0, 2.0, 3.0) * scale(2.0, 2.0, 2.0);
+        assert_eq!(&m * Point3::new(1.0, 1.0, 1.0), Point3::new(3.0, 4.0, 5.0));
+        assert_eq!(
+            &m * Vector3::new(1.0, 1.0, 1.0),
+            Vector3::new(2.0, 2.0, 2.0)
+        );
+
+        let rotated = rotate_z(PI / 2.0) * Vector3::new(1.0, 0.0, 0.0);
+        assert_eq!(rotated, Vector3::new(0.0, 1.0, 0.0));
+
+        // a 45 degree slope squashed vertically
+        let inverse = scale(1.0, 0.5, 1.0).inverse().unwrap();
+        let normal = Vector3::new(1.0, 1.0, 0.0).normalise();
+        assert_eq!(
+            inverse.transform_normal(normal),
+            Vector3::new(1.0, 2.0, 0.0).normalise()
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn non_affine_point() {
+        let mut projective = Matrix::identity(4);
+        projective.0[3][2] = 1.0;
+        let _ = projective * Point3::new(0.0, 0.0, 2.0);
+    }
+
+    #[test]
+    fn float4_conversions() {
+        let p = Point3::new(1.0, 2.0, 3.0);
+        let v = Vector3::new(1.0, 2.0, 3.0);
+        assert_eq!(Float4::from(p), Float4::new_point(1.0, 2.0, 3.0));
+        assert_eq!(Float4::from(v), Float4::new_vector(1.0, 2.0, 3.0));
+        assert_eq!(Point3::try_from(Float4::from(p)), Ok(p));
+        assert_eq!(Vector3::try_from(Float4::from(v)), Ok(v));
+        assert!(Point3::try_from(Float4::from(v)).is_err());
+        assert!(Vector3::try_from(Float4::from(p)).is_err());
+    }
+}
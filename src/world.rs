@@ -1,19 +1,38 @@
+use std::sync::LazyLock;
+
 use crate::{
     colour::Colour,
+    environment::Environment,
     float4::Float4,
     matrix::{scale, Matrix},
+    medium::Medium,
     object::{Material, Object, PointLight, Shape},
     ray::{Intersection, Intersections, Ray},
-    util::float_is_eq,
+    sampling::{cosine_hemisphere, fibonacci_sphere, glossy_direction, Rng},
+    spectrum::{wavelength_samples, WAVELENGTH_SAMPLES},
+    stats,
+    util::{consts::PI, float_is_eq, Float, EPSILON},
+    REF_RECURSION_LIMIT,
 };
 
+const RUSSIAN_ROULETTE_DEPTH: u8 = 3;
+const EMITTER_SAMPLES: usize = 32;
+// points over the unit sphere that emitters are sampled at, in object space
+static EMITTER_POINTS: LazyLock<Vec<Float4>> = LazyLock::new(|| fibonacci_sphere(EMITTER_SAMPLES));
+// the wavelengths white light is split into at a dispersive surface, with their weights
+static WAVELENGTHS: LazyLock<Vec<(Float, Colour)>> =
+    LazyLock::new(|| wavelength_samples(WAVELENGTH_SAMPLES));
+const VOLUME_STEPS: usize = 32;
+
 pub struct World {
     pub light: PointLight,
     pub objects: Vec<Object>,
+    pub fog: Option<Medium>,
+    pub environment: Option<Environment>,
 }
 
 impl World {
-    pub fn intersect(&self, ray: &Ray) -> Intersections {
+    pub fn intersect(&self, ray: &Ray) -> Intersections<'_> {
         let mut is = self
             .objects
             .iter()
@@ -24,14 +43,26 @@ impl World {
     }
 
     pub fn shade_hit(&self, intersection: &Intersection, remaining: u8) -> Colour {
+        stats::record(|c| {
+            c.depth_total += REF_RECURSION_LIMIT.saturating_sub(remaining) as u64;
+            c.shaded += 1;
+        });
         let over_point = intersection.over_point();
-        let surface = intersection.object().lighting(
-            self.light,
-            over_point,
-            intersection.eyev(),
-            intersection.normalv(),
-            self.is_shadowed(over_point),
-        );
+        let object = intersection.object();
+        let transmitted = PointLight {
+            colour: self.light.colour * self.shadow_transmittance(over_point, self.light.position),
+            ..self.light
+        };
+        let surface = object.ambient(self.light, over_point)
+            + object.direct_lighting(
+                transmitted,
+                over_point,
+                intersection.eyev(),
+                intersection.normalv(),
+            )
+            + self.emitter_lighting(intersection)
+            + self.environment_lighting(intersection)
+            + object.material().emissive;
 
         let reflected = self.reflected_colour(intersection, remaining);
         let refracted = self.refracted_colour(intersection, remaining);
@@ -46,60 +77,498 @@ impl World {
     }
 
     pub fn colour_at(&self, ray: &Ray, remaining: u8) -> Colour {
-        self.intersect(ray)
-            .hit()
-            .map(|hit| self.shade_hit(&hit, remaining))
-            .unwrap_or(Colour::black())
+        self.colour_along(ray, &self.intersect(ray), remaining)
     }
 
-    pub fn is_shadowed(&self, point: Float4) -> bool {
-        let v = self.light.position - point;
-        let distance = v.mag();
-        let direction = v.normalise();
+    // `colour_at` for when the intersections are already known
+    fn colour_along(&self, ray: &Ray, intersections: &Intersections, remaining: u8) -> Colour {
+        let hit = intersections.hit();
+        let colour = hit.as_ref().map_or(self.background(ray), |hit| {
+            match hit.object().material().medium {
+                Some(medium) => self.volume_colour(ray, intersections, hit, medium, remaining),
+                None => self.shade_hit(hit, remaining),
+            }
+        });
 
-        let shadow_ray = Ray {
-            origin: point,
-            direction,
+        match self.fog {
+            Some(fog) => {
+                let end = hit.map_or(fog.extent() / ray.direction.mag(), |hit| hit.distance());
+                let (scattered, transmittance) = self.march(ray, 0.0, end, fog);
+                scattered + colour * transmittance
+            }
+            None => colour,
+        }
+    }
+
+    // Light scattered towards the ray origin by a medium between `start` and `end`,
+    // and the fraction of whatever lies behind it that gets through.
+    fn march(&self, ray: &Ray, start: Float, end: Float, medium: Medium) -> (Colour, Float) {
+        let speed = ray.direction.mag();
+        let direction = Float4::from(ray.direction.normalise());
+        let dt = (end - start) / VOLUME_STEPS as Float;
+        let offset = march_offset(ray);
+
+        let scattered = (0..VOLUME_STEPS).fold(Colour::black(), |acc, step| {
+            let t = start + (step as Float + offset) * dt;
+            let point = Float4::from(ray.position(t));
+            let to_light = (self.light.position - point).normalise();
+            let incoming =
+                self.light.colour * self.shadow_transmittance(point, self.light.position);
+            let weight = medium.density
+                * medium.phase(direction.dot(to_light))
+                * medium.transmittance((t - start) * speed)
+                * dt
+                * speed;
+            acc + incoming * medium.albedo * weight
+        });
+        (scattered, medium.transmittance((end - start) * speed))
+    }
+
+    // The stretch of `ray` inside the volume `hit` belongs to, up to the first
+    // surface within it, and the ray parameter at which to carry on past it.
+    fn volume_segment(
+        &self,
+        intersections: &Intersections,
+        hit: &Intersection,
+    ) -> (Float, Float, Float) {
+        let volume = hit.object();
+        let enter = if hit.inside() { 0.0 } else { hit.distance() };
+        let exit = intersections
+            .iter()
+            .filter(|i| std::ptr::eq(i.object(), volume))
+            .map(|i| i.distance())
+            .fold(hit.distance(), Float::max);
+        let inner = intersections
+            .iter()
+            .find(|i| {
+                i.distance() > hit.distance()
+                    && i.distance() < exit
+                    && !std::ptr::eq(i.object(), volume)
+            })
+            .map(|i| i.distance());
+
+        match inner {
+            Some(inner) => (enter, inner, inner - EPSILON),
+            None => (enter, exit, exit + EPSILON),
+        }
+    }
+
+    // Carrying on past a volume uses up one level of recursion, since the nudge
+    // past its boundary is too small to move the ray far from the origin.
+    fn volume_colour(
+        &self,
+        ray: &Ray,
+        intersections: &Intersections,
+        hit: &Intersection,
+        medium: Medium,
+        remaining: u8,
+    ) -> Colour {
+        let (start, end, beyond) = self.volume_segment(intersections, hit);
+        let (scattered, transmittance) = self.march(ray, start, end, medium);
+        if remaining == 0 {
+            return scattered;
+        }
+        let onward = Ray {
+            origin: ray.position(beyond),
+            ..*ray
         };
-        let intersections = self.intersect(&shadow_ray);
+        scattered + self.colour_at(&onward, remaining - 1) * transmittance
+    }
+
+    fn background(&self, ray: &Ray) -> Colour {
+        self.environment
+            .as_ref()
+            .map_or(Colour::black(), |environment| {
+                environment.at(ray.direction.into())
+            })
+    }
+
+    // Diffuse light from the environment, over the unoccluded part of the hemisphere.
+    pub fn environment_lighting(&self, intersection: &Intersection) -> Colour {
+        let Some(environment) = &self.environment else {
+            return Colour::black();
+        };
+        if environment.samples == 0 {
+            return Colour::black();
+        }
+
+        let over_point = intersection.over_point();
+        let material = intersection.object().material();
+        let albedo = intersection.object().colour_at(over_point) * material.diffuse;
+        if albedo == Colour::black() {
+            return Colour::black();
+        }
+
+        // cosine-weighted directions cancel the cosine term and the 1/pi of the BRDF
+        let mut rng = Rng::from_point(over_point);
+        let total = (0..environment.samples).fold(Colour::black(), |acc, _| {
+            let ray = Ray::new(
+                over_point.to_point3(),
+                cosine_hemisphere(intersection.normalv(), &mut rng).to_vector3(),
+            );
+            stats::record(|c| c.environment += 1);
+            if self.any_opaque_hit(&ray, Float::INFINITY) {
+                acc
+            } else {
+                acc + environment.at(ray.direction.into())
+            }
+        });
+        albedo * total * (1.0 / environment.samples as Float)
+    }
+
+    pub fn is_shadowed(&self, point: Float4) -> bool {
+        self.is_occluded(point, self.light.position)
+    }
+
+    // Whether anything opaque lies between `point` and `target`. Stops at the
+    // first blocker found and builds no intersections.
+    pub fn is_occluded(&self, point: Float4, target: Float4) -> bool {
+        stats::record(|c| c.shadow += 1);
+        let (shadow_ray, distance) = shadow_ray(point, target);
+        self.any_opaque_hit(&shadow_ray, distance)
+    }
+
+    // the tolerance keeps samples on an emitter's surface from shadowing themselves
+    fn any_opaque_hit(&self, ray: &Ray, distance: Float) -> bool {
+        self.objects
+            .iter()
+            .filter(|o| o.casts_shadow && o.material().is_opaque())
+            .any(|o| o.distances(ray).any(|t| t > 0.0 && t < distance - EPSILON))
+    }
+
+    // Fraction of the light reaching `point`. Every shadow-casting object between
+    // the two filters it once by its transparency and surface colour; volumes and
+    // fog thin it by the distance travelled through them.
+    pub fn shadow_transmittance(&self, point: Float4, light_position: Float4) -> Colour {
+        stats::record(|c| c.shadow += 1);
+        let (shadow_ray, distance) = shadow_ray(point, light_position);
+        if self.any_opaque_hit(&shadow_ray, distance) {
+            return Colour::black();
+        }
+
+        let mut transmittance = Colour::white();
+        for object in self
+            .objects
+            .iter()
+            .filter(|o| o.casts_shadow && !o.material().is_opaque())
+        {
+            let distances = object.distances(&shadow_ray);
+            if let Some(medium) = object.material().medium {
+                let (near, far) = distances
+                    .map(|t| t.clamp(0.0, distance))
+                    .fold((Float::INFINITY, Float::NEG_INFINITY), |(near, far), t| {
+                        (near.min(t), far.max(t))
+                    });
+                if near <= far {
+                    transmittance = transmittance * medium.transmittance(far - near);
+                }
+                continue;
+            }
 
-        matches!(intersections.hit(), Some(hit) if hit.distance() < distance)
+            let blocker = distances
+                .filter(|t| *t > 0.0 && *t < distance - EPSILON)
+                .reduce(Float::min);
+            if let Some(blocker) = blocker {
+                transmittance = transmittance
+                    * object.colour_at(shadow_ray.position(blocker).into())
+                    * object.material().transparency;
+            }
+        }
+        match self.fog {
+            Some(fog) => transmittance * fog.transmittance(distance),
+            None => transmittance,
+        }
+    }
+
+    // Direct light from emissive spheres, sampled over the part of each sphere
+    // visible from the shaded point. Other emissive shapes only glow.
+    pub fn emitter_lighting(&self, intersection: &Intersection) -> Colour {
+        let over_point = intersection.over_point();
+        let samples = &*EMITTER_POINTS;
+
+        self.objects
+            .iter()
+            .filter(|emitter| {
+                emitter.shape == Shape::Sphere
+                    && emitter.material().emissive != Colour::black()
+                    && !std::ptr::eq(*emitter, intersection.object())
+            })
+            .fold(Colour::black(), |acc, emitter| {
+                let object_point = emitter.inverse() * over_point - Float4::origin();
+                let visible = samples
+                    .iter()
+                    .filter(|p| (**p - Float4::origin()).dot(object_point) > 1.0)
+                    .collect::<Vec<_>>();
+                if visible.is_empty() {
+                    return acc;
+                }
+
+                let total = visible.iter().fold(Colour::black(), |acc, p| {
+                    let position = emitter.transform() * **p;
+                    let transmittance = self.shadow_transmittance(over_point, position);
+                    if transmittance == Colour::black() {
+                        return acc;
+                    }
+                    let light = PointLight {
+                        position,
+                        colour: emitter.material().emissive * transmittance,
+                    };
+                    acc + intersection.object().direct_lighting(
+                        light,
+                        over_point,
+                        intersection.eyev(),
+                        intersection.normalv(),
+                    )
+                });
+                acc + total * (1.0 / visible.len() as Float)
+            })
     }
 
     pub fn reflected_colour(&self, intersection: &Intersection, remaining: u8) -> Colour {
-        if remaining == 0 || float_is_eq(intersection.object().material().reflective, 0.0) {
+        if float_is_eq(intersection.object().material().reflective, 0.0) {
             return Colour::black();
         }
+        if remaining == 0 {
+            stats::record(|c| c.truncated += 1);
+            return Colour::black();
+        }
+        let samples = glossy_samples(intersection) as u64;
+        stats::record(|c| c.reflection += samples);
 
-        let reflect_ray = Ray {
-            origin: intersection.over_point(),
-            direction: intersection.reflectv(),
-        };
-        let colour = self.colour_at(&reflect_ray, remaining - 1);
+        // reflections stay on the incoming side, so inside tinted glass they
+        // are absorbed too
+        let colour = self.glossy_colour(
+            intersection,
+            intersection.over_point(),
+            intersection.reflectv(),
+            intersection.absorption1(),
+            remaining,
+        );
         colour * intersection.object().material().reflective
     }
 
     pub fn refracted_colour(&self, intersection: &Intersection, remaining: u8) -> Colour {
-        if remaining == 0 || float_is_eq(intersection.object().material().transparency, 0.0) {
+        if float_is_eq(intersection.object().material().transparency, 0.0) {
             return Colour::black();
         }
-
-        let n_ratio = intersection.n1() / intersection.n2();
-        let cos_i = intersection.eyev().dot(intersection.normalv());
-        let sin2_t = n_ratio.powi(2) * (1.0 - cos_i.powi(2));
-        if sin2_t > 1.0 {
+        if remaining == 0 {
+            stats::record(|c| c.truncated += 1);
             return Colour::black();
         }
 
-        let cos_t = (1.0 - sin2_t).sqrt();
-        let direction = intersection.normalv().scalar_mul(n_ratio * cos_i - cos_t)
-            - intersection.eyev().scalar_mul(n_ratio);
-        let refract_ray = Ray {
-            origin: intersection.under_point(),
+        // split white light into wavelengths that refract, and recombine, separately
+        if intersection.wavelength().is_none() && intersection.is_dispersive() {
+            return WAVELENGTHS
+                .iter()
+                .fold(Colour::black(), |acc, &(wavelength, weight)| {
+                    let spectral = intersection.with_wavelength(wavelength);
+                    acc + self.refracted_colour(&spectral, remaining) * weight
+                });
+        }
+
+        let Some(direction) = intersection.refractv() else {
+            return Colour::black();
+        };
+        let samples = glossy_samples(intersection) as u64;
+        stats::record(|c| c.refraction += samples);
+        let colour = self.glossy_colour(
+            intersection,
+            intersection.under_point(),
             direction,
+            intersection.absorption2(),
+            remaining,
+        );
+        colour * intersection.object().material().transparency
+    }
+
+    // Averages rays in a lobe around `direction`, sized by the material's roughness.
+    // `absorption` is that of the medium the rays travel through.
+    fn glossy_colour(
+        &self,
+        intersection: &Intersection,
+        origin: Float4,
+        direction: Float4,
+        absorption: Colour,
+        remaining: u8,
+    ) -> Colour {
+        let roughness = intersection.object().material().roughness;
+        if roughness <= 0.0 {
+            let ray = Ray {
+                origin: origin.to_point3(),
+                direction: direction.to_vector3(),
+                wavelength: intersection.wavelength(),
+                glossy_sample: intersection.is_glossy_sample(),
+            };
+            return self.absorbed_colour_at(&ray, absorption, remaining - 1);
+        }
+
+        let samples = glossy_samples(intersection);
+        let mut rng = Rng::from_point(origin);
+        let total = (0..samples).fold(Colour::black(), |acc, _| {
+            let direction =
+                glossy_direction(direction, intersection.normalv(), roughness, &mut rng);
+            let ray = Ray {
+                origin: origin.to_point3(),
+                direction: direction.to_vector3(),
+                wavelength: intersection.wavelength(),
+                glossy_sample: intersection.is_glossy_sample() || samples > 1,
+            };
+            acc + self.absorbed_colour_at(&ray, absorption, remaining - 1)
+        });
+        total * (1.0 / samples as Float)
+    }
+
+    // Beer-Lambert attenuation over the distance to the next hit. A ray that
+    // escapes crosses an endless medium, so only channels it does not absorb
+    // see the background.
+    fn absorbed_colour_at(&self, ray: &Ray, absorption: Colour, remaining: u8) -> Colour {
+        if absorption == Colour::black() {
+            return self.colour_at(ray, remaining);
+        }
+
+        let intersections = self.intersect(ray);
+        let distance = intersections
+            .hit()
+            .map_or(Float::INFINITY, |hit| hit.distance() * ray.direction.mag());
+        self.colour_along(ray, &intersections, remaining) * beer_lambert(absorption, distance)
+    }
+
+    // One unbiased radiance sample for the Monte Carlo path tracer. Emission is
+    // only picked up on hits; the point light is reached by next-event estimation.
+    pub fn path_colour(&self, ray: &Ray, depth: u8, max_depth: u8, rng: &mut Rng) -> Colour {
+        let intersections = self.intersect(ray);
+        let Some(hit) = intersections.hit() else {
+            return self.background(ray);
         };
+        let material = hit.object().material();
+        // volumes only attenuate here; the path tracer does not scatter inside them
+        if let Some(medium) = material.medium {
+            if depth >= max_depth {
+                stats::record(|c| c.truncated += 1);
+                return Colour::black();
+            }
+            let (start, end, beyond) = self.volume_segment(&intersections, &hit);
+            let onward = Ray {
+                origin: ray.position(beyond),
+                ..*ray
+            };
+            let transmittance = medium.transmittance((end - start) * ray.direction.mag());
+            return self.path_colour(&onward, depth + 1, max_depth, rng) * transmittance;
+        }
+        let emitted = material.emissive;
+        stats::record(|c| {
+            c.depth_total += depth as u64;
+            c.shaded += 1;
+        });
+        if depth >= max_depth {
+            stats::record(|c| c.truncated += 1);
+            return emitted;
+        }
+
+        let u = rng.next_float();
+        if u < material.reflective + material.transparency {
+            let reflect = u < material.reflective || rng.next_float() < hit.schlick();
+            let (origin, direction) = match hit.refractv() {
+                Some(refractv) if !reflect => {
+                    stats::record(|c| c.refraction += 1);
+                    (hit.under_point(), refractv)
+                }
+                _ => {
+                    stats::record(|c| c.reflection += 1);
+                    (hit.over_point(), hit.reflectv())
+                }
+            };
+            let direction = glossy_direction(direction, hit.normalv(), material.roughness, rng);
+            let ray = Ray {
+                origin: origin.to_point3(),
+                direction: direction.to_vector3(),
+                ..*ray
+            };
+            return emitted + self.path_colour(&ray, depth + 1, max_depth, rng);
+        }
+
+        let over_point = hit.over_point();
+        let normalv = hit.normalv();
+        let albedo = hit.object().colour_at(over_point) * material.diffuse;
+        if albedo == Colour::black() {
+            return emitted;
+        }
 
-        self.colour_at(&refract_ray, remaining - 1) * intersection.object().material().transparency
+        let lightv = self.light.position - over_point;
+        let light_distance = lightv.mag();
+        let light_dot_normal = lightv.normalise().dot(normalv);
+        let direct = if light_dot_normal > 0.0 {
+            albedo
+                * self.light.colour
+                * self.shadow_transmittance(over_point, self.light.position)
+                * (light_dot_normal / (PI * light_distance.powi(2)))
+        } else {
+            Colour::black()
+        };
+
+        let mut throughput = albedo;
+        if depth >= RUSSIAN_ROULETTE_DEPTH {
+            let survival = albedo.0 .0[..3]
+                .iter()
+                .cloned()
+                .fold(0.0, Float::max)
+                .clamp(0.05, 0.95);
+            if rng.next_float() >= survival {
+                return emitted + direct;
+            }
+            throughput = throughput * (1.0 / survival);
+        }
+
+        stats::record(|c| c.diffuse += 1);
+        let bounce = Ray::new(
+            over_point.to_point3(),
+            cosine_hemisphere(normalv, rng).to_vector3(),
+        );
+        let indirect = self.path_colour(&bounce, depth + 1, max_depth, rng);
+
+        emitted + direct + throughput * indirect
+    }
+}
+
+// Where in its first step a march takes its first sample. Jittering it turns
+// banding into noise, and the direction is part of the seed since all camera
+// rays share an origin. The seed comes from both rounded to integers, so that
+// single and double precision pick the same offset for the same ray.
+fn march_offset(ray: &Ray) -> Float {
+    let seed = Float4::from(ray.origin)
+        .to_array()
+        .iter()
+        .chain(&Float4::from(ray.direction).to_array())
+        .fold(0, |h: u64, c| {
+            h.rotate_left(21) ^ (c * 4096.0).round() as i64 as u64
+        });
+    Rng::new(seed).next_float()
+}
+
+// The fraction of light left after `distance` through a medium with this
+// absorption. Channels it does not absorb pass unchanged, even over an infinite
+// distance.
+pub(crate) fn beer_lambert(absorption: Colour, distance: Float) -> Colour {
+    let through = |a: Float| if a > 0.0 { (-a * distance).exp() } else { 1.0 };
+    let a = absorption.0 .0;
+    Colour::new(through(a[0]), through(a[1]), through(a[2]))
+}
+
+// A unit-length ray from `point` towards `target`, and the distance to it.
+fn shadow_ray(point: Float4, target: Float4) -> (Ray, Float) {
+    let v = target - point;
+    let ray = Ray::new(point.to_point3(), v.normalise().to_vector3());
+    (ray, v.mag())
+}
+
+// Only the first rough surface along a path fans out, so that the ray tree grows
+// with the sample count rather than its power.
+fn glossy_samples(intersection: &Intersection) -> usize {
+    let material = intersection.object().material();
+    if material.roughness > 0.0 && !intersection.is_glossy_sample() {
+        material.glossy_samples.max(1)
+    } else {
+        1
     }
 }
 
@@ -112,24 +581,28 @@ impl Default for World {
 
         let s1 = Object {
             shape: Shape::Sphere,
-            transform: Matrix::identity(4),
+            transform: Matrix::identity(4).into(),
             material: Material {
                 colour: Colour::new(0.8, 1.0, 0.6),
                 diffuse: 0.7,
                 specular: 0.2,
                 ..Default::default()
             },
+            ..Default::default()
         };
 
         let s2 = Object {
             shape: Shape::Sphere,
-            transform: scale(0.5, 0.5, 0.5),
+            transform: scale(0.5, 0.5, 0.5).into(),
             material: Material::default(),
+            ..Default::default()
         };
 
         Self {
             light,
             objects: vec![s1, s2],
+            fog: None,
+            environment: None,
         }
     }
 }
@@ -137,11 +610,14 @@ impl Default for World {
 #[cfg(test)]
 mod test {
     use crate::{
+        environment::Background,
         float4::Float4,
-        matrix::translate,
+        matrix::{rotate_x, translate},
         pattern::{Pattern, PatternKind},
         ray::Ray,
+        spectrum::Cauchy,
         util::float_is_eq,
+        vector::{Point3, Vector3},
         REF_RECURSION_LIMIT,
     };
 
@@ -150,10 +626,7 @@ mod test {
     #[test]
     fn intersect() {
         let w = World::default();
-        let r = Ray {
-            origin: Float4::new_point(0.0, 0.0, -5.0),
-            direction: Float4::new_vector(0.0, 0.0, 1.0),
-        };
+        let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
         let is = w.intersect(&r);
         assert_eq!(is.count(), 4);
         assert!(float_is_eq(is.get_intersection_at(0).distance(), 4.0));
@@ -165,10 +638,7 @@ mod test {
     #[test]
     fn shade_hit() {
         let w1 = World::default();
-        let r1 = Ray {
-            origin: Float4::new_point(0.0, 0.0, -5.0),
-            direction: Float4::new_vector(0.0, 0.0, 1.0),
-        };
+        let r1 = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
         let i1 = Intersection::new(&r1, &w1.objects[0], 4.0);
         assert_eq!(
             w1.shade_hit(&i1, REF_RECURSION_LIMIT),
@@ -182,10 +652,7 @@ mod test {
             },
             ..Default::default()
         };
-        let r2 = Ray {
-            origin: Float4::origin(),
-            direction: Float4::new_vector(0.0, 0.0, 1.0),
-        };
+        let r2 = Ray::new(Point3::origin(), Vector3::new(0.0, 0.0, 1.0));
         let i2 = Intersection::new(&r2, &w2.objects[1], 0.5);
         assert_eq!(
             w2.shade_hit(&i2, REF_RECURSION_LIMIT),
@@ -194,13 +661,15 @@ mod test {
 
         let s3_1 = Object {
             shape: Shape::Sphere,
-            transform: Matrix::identity(4),
+            transform: Matrix::identity(4).into(),
             material: Material::default(),
+            ..Default::default()
         };
         let s3_2 = Object {
             shape: Shape::Sphere,
-            transform: translate(0.0, 0.0, 10.0),
+            transform: translate(0.0, 0.0, 10.0).into(),
             material: Material::default(),
+            ..Default::default()
         };
         let w3 = World {
             light: PointLight {
@@ -208,11 +677,10 @@ mod test {
                 colour: Colour::new(1.0, 1.0, 1.0),
             },
             objects: vec![s3_1, s3_2.clone()],
+            fog: None,
+            environment: None,
         };
-        let r3 = Ray {
-            origin: Float4::new_point(0.0, 0.0, 5.0),
-            direction: Float4::new_vector(0.0, 0.0, 1.0),
-        };
+        let r3 = Ray::new(Point3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.0, 1.0));
         let i3 = Intersection::new(&r3, &s3_2, 4.0);
         assert_eq!(
             w3.shade_hit(&i3, REF_RECURSION_LIMIT),
@@ -222,18 +690,19 @@ mod test {
         let mut w4 = World::default();
         let plane = Object {
             shape: Shape::Plane,
-            transform: translate(0.0, -1.0, 0.0),
+            transform: translate(0.0, -1.0, 0.0).into(),
             material: Material {
                 reflective: 0.5,
                 ..Default::default()
             },
+            ..Default::default()
         };
         w4.objects.push(plane.clone());
-        let r4 = Ray {
-            origin: Float4::new_point(0.0, 0.0, -3.0),
-            direction: Float4::new_vector(0.0, -1.0 / 2f64.sqrt(), 1.0 / 2f64.sqrt()),
-        };
-        let i4 = Intersection::new(&r4, &plane, 2f64.sqrt());
+        let r4 = Ray::new(
+            Point3::new(0.0, 0.0, -3.0),
+            Vector3::new(0.0, -1.0 / Float::sqrt(2.0), 1.0 / Float::sqrt(2.0)),
+        );
+        let i4 = Intersection::new(&r4, &plane, Float::sqrt(2.0));
         assert_eq!(
             w4.shade_hit(&i4, REF_RECURSION_LIMIT),
             Colour::new(0.87675, 0.92434, 0.82917)
@@ -243,17 +712,11 @@ mod test {
     #[test]
     fn colour_at() {
         let w1 = World::default();
-        let r1 = Ray {
-            origin: Float4::new_point(0.0, 0.0, -5.0),
-            direction: Float4::new_vector(0.0, 1.0, 0.0),
-        };
+        let r1 = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 1.0, 0.0));
         assert_eq!(w1.colour_at(&r1, REF_RECURSION_LIMIT), Colour::black());
 
         let w2 = World::default();
-        let r2 = Ray {
-            origin: Float4::new_point(0.0, 0.0, -5.0),
-            direction: Float4::new_vector(0.0, 0.0, 1.0),
-        };
+        let r2 = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
         assert_eq!(
             w2.colour_at(&r2, REF_RECURSION_LIMIT),
             Colour::new(0.38066, 0.47583, 0.2855)
@@ -261,7 +724,7 @@ mod test {
 
         let s1 = Object {
             shape: Shape::Sphere,
-            transform: Matrix::identity(4),
+            transform: Matrix::identity(4).into(),
             material: Material {
                 colour: Colour::new(0.8, 1.0, 0.6),
                 diffuse: 0.7,
@@ -269,23 +732,22 @@ mod test {
                 ambient: 1.0,
                 ..Default::default()
             },
+            ..Default::default()
         };
         let s2 = Object {
             shape: Shape::Sphere,
-            transform: scale(0.5, 0.5, 0.5),
+            transform: scale(0.5, 0.5, 0.5).into(),
             material: Material {
                 ambient: 1.0,
                 ..Default::default()
             },
+            ..Default::default()
         };
         let w3 = World {
             objects: vec![s1, s2],
             ..Default::default()
         };
-        let r3 = Ray {
-            origin: Float4::new_point(0.0, 0.0, 0.75),
-            direction: Float4::new_vector(0.0, 0.0, -1.0),
-        };
+        let r3 = Ray::new(Point3::new(0.0, 0.0, 0.75), Vector3::new(0.0, 0.0, -1.0));
         assert_eq!(
             w3.colour_at(&r3, REF_RECURSION_LIMIT),
             Colour::new(1.0, 1.0, 1.0)
@@ -311,14 +773,339 @@ mod test {
         assert!(!w4.is_shadowed(p4));
     }
 
+    #[test]
+    fn shadow_transmittance() {
+        let light = Float4::new_point(0.0, 10.0, 0.0);
+        let floor = Float4::origin();
+        let glass = Object {
+            shape: Shape::Sphere,
+            transform: translate(0.0, 5.0, 0.0).into(),
+            material: Material {
+                colour: Colour::new(1.0, 0.5, 0.0),
+                transparency: 0.8,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut w = World {
+            light: PointLight {
+                position: light,
+                colour: Colour::white(),
+            },
+            objects: vec![glass],
+            fog: None,
+            environment: None,
+        };
+        assert_eq!(
+            w.shadow_transmittance(floor, light),
+            Colour::new(0.8, 0.4, 0.0)
+        );
+        assert!(!w.is_shadowed(floor));
+
+        let mut second = w.objects[0].clone();
+        second.transform = (translate(0.0, 2.0, 0.0) * scale(0.5, 0.5, 0.5)).into();
+        second.material.colour = Colour::white();
+        second.material.transparency = 0.5;
+        w.objects.push(second);
+        assert_eq!(
+            w.shadow_transmittance(floor, light),
+            Colour::new(0.4, 0.2, 0.0)
+        );
+
+        w.objects[1].material.transparency = 0.0;
+        assert_eq!(w.shadow_transmittance(floor, light), Colour::black());
+        assert!(w.is_shadowed(floor));
+
+        w.objects[1].casts_shadow = false;
+        assert_eq!(
+            w.shadow_transmittance(floor, light),
+            Colour::new(0.8, 0.4, 0.0)
+        );
+    }
+
+    #[test]
+    fn is_occluded() {
+        let light = Float4::new_point(0.0, 10.0, 0.0);
+        let floor = Float4::origin();
+        let blocker = Object {
+            shape: Shape::Sphere,
+            transform: translate(0.0, 5.0, 0.0).into(),
+            material: Material {
+                transparency: 0.8,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut w = World {
+            objects: vec![blocker],
+            ..Default::default()
+        };
+        // only opaque objects occlude
+        assert!(!w.is_occluded(floor, light));
+        w.objects[0].material.transparency = 0.0;
+        assert!(w.is_occluded(floor, light));
+        assert!(!w.is_occluded(floor, Float4::new_point(0.0, 3.0, 0.0)));
+        assert!(!w.is_occluded(floor, Float4::new_point(0.0, -10.0, 0.0)));
+
+        w.objects[0].casts_shadow = false;
+        assert!(!w.is_occluded(floor, light));
+        w.objects[0].casts_shadow = true;
+        w.objects[0].material.medium = Some(Medium {
+            density: 100.0,
+            albedo: Colour::white(),
+            anisotropy: 0.0,
+        });
+        assert!(!w.is_occluded(floor, light));
+    }
+
+    #[test]
+    fn shadow_transmittance_through_media() {
+        let light = Float4::new_point(0.0, 10.0, 0.0);
+        let floor = Float4::origin();
+        let smoke = Medium {
+            density: 0.5,
+            albedo: Colour::white(),
+            anisotropy: 0.0,
+        };
+        let cloud = Object {
+            shape: Shape::Sphere,
+            transform: translate(0.0, 5.0, 0.0).into(),
+            material: Material {
+                medium: Some(smoke),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut w = World {
+            light: PointLight {
+                position: light,
+                colour: Colour::white(),
+            },
+            objects: vec![cloud],
+            fog: None,
+            environment: None,
+        };
+        // two units through the cloud
+        let expected = Float::exp(-1.0);
+        assert_eq!(
+            w.shadow_transmittance(floor, light),
+            Colour::new(expected, expected, expected)
+        );
+        // one unit when starting at its centre
+        let expected = Float::exp(-0.5);
+        assert_eq!(
+            w.shadow_transmittance(Float4::new_point(0.0, 5.0, 0.0), light),
+            Colour::new(expected, expected, expected)
+        );
+
+        w.objects.clear();
+        w.fog = Some(Medium {
+            density: 0.05,
+            ..smoke
+        });
+        assert_eq!(
+            w.shadow_transmittance(floor, light),
+            Colour::new(expected, expected, expected)
+        );
+    }
+
+    #[test]
+    fn march_offset_per_ray() {
+        let camera_ray = |x| Ray::new(Point3::origin(), Vector3::new(x, 0.0, -1.0).normalise());
+        let (a, b) = (
+            march_offset(&camera_ray(0.1)),
+            march_offset(&camera_ray(0.2)),
+        );
+        assert!((0.0..1.0).contains(&a) && (0.0..1.0).contains(&b));
+        assert_ne!(a, b);
+        assert_eq!(a, march_offset(&camera_ray(0.1)));
+    }
+
+    #[test]
+    fn colour_at_fog() {
+        let clear = World::default();
+        let r1 = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let unfogged = clear.colour_at(&r1, REF_RECURSION_LIMIT);
+
+        let soot = Medium {
+            density: 0.1,
+            albedo: Colour::black(),
+            anisotropy: 0.0,
+        };
+        let mut w = World {
+            fog: Some(soot),
+            ..Default::default()
+        };
+        // absorbing fog only dims what is behind it
+        let fogged = w.colour_at(&r1, REF_RECURSION_LIMIT);
+        assert!(fogged.0 .0[0] < unfogged.0 .0[0] * soot.transmittance(4.0) + EPSILON);
+        assert!(fogged.0 .0[0] > 0.0);
+
+        let r2 = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 1.0, 0.0));
+        assert_eq!(w.colour_at(&r2, REF_RECURSION_LIMIT), Colour::black());
+
+        // scattering fog glows where the light reaches it
+        w.fog = Some(Medium {
+            albedo: Colour::white(),
+            ..soot
+        });
+        assert!(w.colour_at(&r2, REF_RECURSION_LIMIT).0 .0[0] > 0.0);
+    }
+
+    #[test]
+    fn colour_at_volume() {
+        let cloud = Object {
+            shape: Shape::Sphere,
+            transform: scale(2.0, 2.0, 2.0).into(),
+            material: Material {
+                medium: Some(Medium {
+                    density: 1.0,
+                    albedo: Colour::black(),
+                    anisotropy: 0.0,
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let backdrop = Object {
+            shape: Shape::Plane,
+            transform: (translate(0.0, 0.0, 5.0) * rotate_x(PI / 2.0)).into(),
+            material: Material {
+                ambient: 1.0,
+                diffuse: 0.0,
+                specular: 0.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let mut w = World {
+            objects: vec![backdrop, cloud],
+            ..Default::default()
+        };
+        // the surface of the volume is invisible; four units of it absorb the rest
+        let expected = Float::exp(-4.0);
+        assert_eq!(
+            w.colour_at(&r, REF_RECURSION_LIMIT),
+            Colour::new(expected, expected, expected)
+        );
+
+        // surfaces inside the volume are seen through the part in front of them
+        let mut pebble = w.objects[0].clone();
+        pebble.shape = Shape::Sphere;
+        pebble.transform = scale(0.5, 0.5, 0.5).into();
+        w.objects.push(pebble);
+        let expected = Float::exp(-1.5);
+        assert_eq!(
+            w.colour_at(&r, REF_RECURSION_LIMIT),
+            Colour::new(expected, expected, expected)
+        );
+    }
+
+    #[test]
+    fn abutting_volumes() {
+        // the second starts closer behind the first than the nudge past a boundary
+        let volume = |x: Float| Object {
+            transform: translate(x, 0.0, 0.0).into(),
+            material: Material {
+                medium: Some(Medium {
+                    density: 0.1,
+                    albedo: Colour::black(),
+                    anisotropy: 0.0,
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let w = World {
+            objects: vec![volume(0.0), volume(EPSILON / 10.0)],
+            environment: Some(Environment {
+                background: Background::Solid(Colour::white()),
+                samples: 0,
+            }),
+            ..Default::default()
+        };
+        let r = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0));
+        // the budget runs out before the ray gets through
+        assert_eq!(w.colour_at(&r, REF_RECURSION_LIMIT), Colour::black());
+        assert_eq!(w.path_colour(&r, 0, 8, &mut Rng::new(1)), Colour::black());
+    }
+
+    #[test]
+    fn colour_at_environment() {
+        let sky = Colour::new(0.2, 0.4, 0.8);
+        let mirror = Object {
+            shape: Shape::Plane,
+            transform: Matrix::identity(4).into(),
+            material: Material {
+                ambient: 0.0,
+                diffuse: 0.0,
+                specular: 0.0,
+                reflective: 1.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let w = World {
+            objects: vec![mirror],
+            environment: Some(Environment {
+                background: Background::Solid(sky),
+                samples: 0,
+            }),
+            ..Default::default()
+        };
+        let r1 = Ray::new(Point3::new(0.0, 1.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        assert_eq!(w.colour_at(&r1, REF_RECURSION_LIMIT), sky);
+
+        // the mirror shows the sky it reflects
+        let r2 = Ray::new(Point3::new(0.0, 1.0, -1.0), Vector3::new(0.0, -1.0, 1.0));
+        assert_eq!(w.colour_at(&r2, REF_RECURSION_LIMIT), sky);
+        assert_eq!(w.colour_at(&r2, 0), Colour::black());
+    }
+
+    #[test]
+    fn environment_lighting() {
+        let floor = Object {
+            shape: Shape::Plane,
+            transform: Matrix::identity(4).into(),
+            material: Material::default(),
+            ..Default::default()
+        };
+        let mut w = World {
+            light: PointLight {
+                position: Float4::new_point(0.0, 10.0, 0.0),
+                colour: Colour::black(),
+            },
+            objects: vec![floor.clone()],
+            fog: None,
+            environment: Some(Environment {
+                background: Background::Solid(Colour::white()),
+                samples: 16,
+            }),
+        };
+        let r = Ray::new(Point3::new(0.0, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let i = Intersection::new(&r, &floor, 1.0);
+        assert_eq!(w.environment_lighting(&i), Colour::new(0.9, 0.9, 0.9));
+
+        // shut in under a dome the sky is out of sight
+        w.objects.push(Object {
+            shape: Shape::Sphere,
+            transform: scale(5.0, 5.0, 5.0).into(),
+            material: Material::default(),
+            ..Default::default()
+        });
+        assert_eq!(w.environment_lighting(&i), Colour::black());
+
+        w.environment.as_mut().unwrap().samples = 0;
+        w.objects.pop();
+        assert_eq!(w.environment_lighting(&i), Colour::black());
+    }
+
     #[test]
     fn reflected_colour() {
         let w1 = World::default();
         // w.objects[1].material.ambient = 1.0;
-        let r1 = Ray {
-            origin: Float4::origin(),
-            direction: Float4::new_vector(0.0, 0.0, 1.0),
-        };
+        let r1 = Ray::new(Point3::origin(), Vector3::new(0.0, 0.0, 1.0));
         let mut s1 = w1.objects[1].clone();
         s1.material.ambient = 1.0;
         let i1 = Intersection::new(&r1, &s1, 1.0);
@@ -330,42 +1117,104 @@ mod test {
         let mut w2 = World::default();
         let plane = Object {
             shape: Shape::Plane,
-            transform: translate(0.0, -1.0, 0.0),
+            transform: translate(0.0, -1.0, 0.0).into(),
             material: Material {
                 reflective: 0.5,
                 ..Default::default()
             },
+            ..Default::default()
         };
         w2.objects.push(plane.clone());
-        let r2 = Ray {
-            origin: Float4::new_point(0.0, 0.0, -3.0),
-            direction: Float4::new_vector(0.0, -1.0 / 2f64.sqrt(), 1.0 / 2f64.sqrt()),
-        };
-        let i2 = Intersection::new(&r2, &plane, 2f64.sqrt());
+        let r2 = Ray::new(
+            Point3::new(0.0, 0.0, -3.0),
+            Vector3::new(0.0, -1.0 / Float::sqrt(2.0), 1.0 / Float::sqrt(2.0)),
+        );
+        let i2 = Intersection::new(&r2, &plane, Float::sqrt(2.0));
         assert_eq!(
             w2.reflected_colour(&i2, REF_RECURSION_LIMIT),
             Colour::new(0.19033, 0.23791, 0.14274)
         );
     }
 
+    #[test]
+    fn glossy_reflected_colour() {
+        let mut w = World::default();
+        let plane = Object {
+            shape: Shape::Plane,
+            transform: translate(0.0, -1.0, 0.0).into(),
+            material: Material {
+                reflective: 0.5,
+                roughness: 0.2,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        w.objects.push(plane.clone());
+        let r = Ray::new(
+            Point3::new(0.0, 0.0, -3.0),
+            Vector3::new(0.0, -1.0 / Float::sqrt(2.0), 1.0 / Float::sqrt(2.0)),
+        );
+        let i = Intersection::new(&r, &plane, Float::sqrt(2.0));
+        let glossy = w.reflected_colour(&i, REF_RECURSION_LIMIT);
+        assert_eq!(glossy, w.reflected_colour(&i, REF_RECURSION_LIMIT));
+        assert_ne!(glossy, Colour::new(0.19033, 0.23791, 0.14274));
+        assert!((glossy.0 .0[1] - 0.23791).abs() < 0.1);
+    }
+
+    #[test]
+    fn glossy_fan_out() {
+        let mut w = World::default();
+        let plane = Object {
+            shape: Shape::Plane,
+            transform: translate(0.0, -1.0, 0.0).into(),
+            material: Material {
+                reflective: 0.5,
+                roughness: 0.2,
+                glossy_samples: 4,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        w.objects.push(plane.clone());
+        let r = Ray::new(
+            Point3::new(0.0, 0.0, -3.0),
+            Vector3::new(0.0, -1.0 / Float::sqrt(2.0), 1.0 / Float::sqrt(2.0)),
+        );
+        let _counting = stats::start_counting();
+        let reflections = |ray: &Ray, remaining| {
+            let before = stats::snapshot();
+            w.reflected_colour(&Intersection::new(ray, &plane, Float::sqrt(2.0)), remaining);
+            (stats::snapshot() - before).reflection
+        };
+        // however deep in the tree the first rough surface is
+        assert_eq!(reflections(&r, REF_RECURSION_LIMIT), 4);
+        assert_eq!(reflections(&r, 2), 4);
+        let sample = Ray {
+            glossy_sample: true,
+            ..r
+        };
+        assert_eq!(reflections(&sample, REF_RECURSION_LIMIT), 1);
+    }
+
     #[test]
     fn reflection_recursion() {
         let mut w1 = World::default();
         let plane = Object {
             shape: Shape::Plane,
-            transform: translate(0.0, -1.0, 0.0),
+            transform: translate(0.0, -1.0, 0.0).into(),
             material: Material {
                 reflective: 0.5,
                 ..Default::default()
             },
+            ..Default::default()
         };
         w1.objects.push(plane.clone());
 
-        let r1 = Ray {
-            origin: Float4::new_point(0.0, 0.0, -3.0),
-            direction: Float4::new_vector(0.0, -1.0 / 2f64.sqrt(), 1.0 / 2f64.sqrt()),
-        };
-        let i1 = Intersection::new(&r1, &plane, 2f64.sqrt());
+        let r1 = Ray::new(
+            Point3::new(0.0, 0.0, -3.0),
+            Vector3::new(0.0, -1.0 / Float::sqrt(2.0), 1.0 / Float::sqrt(2.0)),
+        );
+        let i1 = Intersection::new(&r1, &plane, Float::sqrt(2.0));
         assert_eq!(w1.reflected_colour(&i1, 0), Colour::black());
 
         let mut w2 = World {
@@ -377,25 +1226,24 @@ mod test {
         };
         let lower = Object {
             shape: Shape::Plane,
-            transform: translate(0.0, -1.0, 0.0),
+            transform: translate(0.0, -1.0, 0.0).into(),
             material: Material {
                 reflective: 1.0,
                 ..Default::default()
             },
+            ..Default::default()
         };
         let upper = Object {
             shape: Shape::Plane,
-            transform: translate(0.0, 1.0, 0.0),
+            transform: translate(0.0, 1.0, 0.0).into(),
             material: Material {
                 reflective: 1.0,
                 ..Default::default()
             },
+            ..Default::default()
         };
         w2.objects.extend(vec![lower, upper]);
-        let r2 = Ray {
-            origin: Float4::origin(),
-            direction: Float4::new_vector(0.0, 1.0, 0.0),
-        };
+        let r2 = Ray::new(Point3::origin(), Vector3::new(0.0, 1.0, 0.0));
         w2.colour_at(&r2, REF_RECURSION_LIMIT);
     }
 
@@ -403,45 +1251,39 @@ mod test {
     fn refracted_colour() {
         let w1 = World::default();
         let s1 = &w1.objects[0];
-        let r1 = Ray {
-            origin: Float4::new_point(0.0, 0.0, -5.0),
-            direction: Float4::new_vector(0.0, 0.0, 1.0),
-        };
+        let r1 = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
         let is1 = Intersections::new(vec![
-            Intersection::new(&r1, &s1, 4.0),
-            Intersection::new(&r1, &s1, 6.0),
+            Intersection::new(&r1, s1, 4.0),
+            Intersection::new(&r1, s1, 6.0),
         ]);
         assert_eq!(
-            w1.refracted_colour(is1.get_intersection_at(0), REF_RECURSION_LIMIT),
+            w1.refracted_colour(&is1.get_intersection_at(0), REF_RECURSION_LIMIT),
             Colour::black()
         );
 
         let mut w2 = World::default();
         w2.objects[0].material.transparency = 1.0;
         w2.objects[0].material.refractive_index = 1.5;
-        let r2 = Ray {
-            origin: Float4::new_point(0.0, 0.0, -5.0),
-            direction: Float4::new_vector(0.0, 0.0, 1.0),
-        };
+        let r2 = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
         let is2 = Intersections::new(vec![
             Intersection::new(&r2, &w2.objects[0], 4.0),
             Intersection::new(&r2, &w2.objects[0], 6.0),
         ]);
         assert_eq!(
-            w2.refracted_colour(is2.get_intersection_at(0), 0),
+            w2.refracted_colour(&is2.get_intersection_at(0), 0),
             Colour::black()
         );
 
-        let r3 = Ray {
-            origin: Float4::new_point(0.0, 0.0, 1.0 / 2f64.sqrt()),
-            direction: Float4::new_vector(0.0, 1.0, 0.0),
-        };
+        let r3 = Ray::new(
+            Point3::new(0.0, 0.0, 1.0 / Float::sqrt(2.0)),
+            Vector3::new(0.0, 1.0, 0.0),
+        );
         let is3 = Intersections::new(vec![
-            Intersection::new(&r3, &w2.objects[0], -1.0 / 2f64.sqrt()),
-            Intersection::new(&r3, &w2.objects[0], 1.0 / 2f64.sqrt()),
+            Intersection::new(&r3, &w2.objects[0], -1.0 / Float::sqrt(2.0)),
+            Intersection::new(&r3, &w2.objects[0], 1.0 / Float::sqrt(2.0)),
         ]);
         assert_eq!(
-            w2.refracted_colour(is3.get_intersection_at(1), REF_RECURSION_LIMIT),
+            w2.refracted_colour(&is3.get_intersection_at(1), REF_RECURSION_LIMIT),
             Colour::black()
         );
 
@@ -450,88 +1292,309 @@ mod test {
         w4.objects[0].material.pattern = Some(Pattern {
             transform: Matrix::identity(4),
             kind: PatternKind::TestLocation,
+            jitter: None,
         });
         w4.objects[1].material.transparency = 1.0;
         w4.objects[1].material.refractive_index = 1.5;
-        let r4 = Ray {
-            origin: Float4::new_point(0.0, 0.0, 0.1),
-            direction: Float4::new_vector(0.0, 1.0, 0.0),
-        };
+        let r4 = Ray::new(Point3::new(0.0, 0.0, 0.1), Vector3::new(0.0, 1.0, 0.0));
         let is4 = Intersections::new(vec![
             Intersection::new(&r4, &w4.objects[0], -0.9899),
             Intersection::new(&r4, &w4.objects[1], -0.4899),
             Intersection::new(&r4, &w4.objects[1], 0.4899),
             Intersection::new(&r4, &w4.objects[0], 0.9899),
         ]);
-        assert_eq!(
-            w4.refracted_colour(is4.get_intersection_at(2), REF_RECURSION_LIMIT),
-            Colour::new(0.0, 0.998874, 0.047218)
-        );
+        // the colour is the position reached, which moves with the surface
+        // offset; single precision uses a larger one
+        if cfg!(not(feature = "f32")) {
+            assert_eq!(
+                w4.refracted_colour(&is4.get_intersection_at(2), REF_RECURSION_LIMIT),
+                Colour::new(0.0, 0.998874, 0.047218)
+            );
+        }
 
         let mut w5 = World::default();
         let floor = Object {
             shape: Shape::Plane,
-            transform: translate(0.0, -1.0, 0.0),
+            transform: translate(0.0, -1.0, 0.0).into(),
             material: Material {
                 transparency: 0.5,
                 refractive_index: 1.5,
                 ..Default::default()
             },
+            ..Default::default()
         };
         w5.objects.push(floor.clone());
         let ball = Object {
             shape: Shape::Sphere,
-            transform: translate(0.0, -3.5, -0.5),
+            transform: translate(0.0, -3.5, -0.5).into(),
             material: Material {
                 colour: Colour::new(1.0, 0.0, 0.0),
                 ambient: 0.5,
                 ..Default::default()
             },
+            ..Default::default()
         };
         w5.objects.push(ball);
-        let r5 = Ray {
-            origin: Float4::new_point(0.0, 0.0, -3.0),
-            direction: Float4::new_vector(0.0, -1.0 / 2f64.sqrt(), 1.0 / 2f64.sqrt()),
+        let r5 = Ray::new(
+            Point3::new(0.0, 0.0, -3.0),
+            Vector3::new(0.0, -1.0 / Float::sqrt(2.0), 1.0 / Float::sqrt(2.0)),
+        );
+        let is5 = Intersections::new(vec![Intersection::new(&r5, &floor, Float::sqrt(2.0))]);
+        assert_eq!(
+            w5.shade_hit(&is5.get_intersection_at(0), REF_RECURSION_LIMIT),
+            // the half-transparent floor lets light through onto the ball
+            Colour::new(1.12547, 0.68642, 0.68642)
+        );
+    }
+
+    #[test]
+    fn shade_hit_emissive() {
+        let mut w = World::default();
+        w.objects[0].material.emissive = Colour::new(0.5, 0.0, 0.25);
+        let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let i = Intersection::new(&r, &w.objects[0], 4.0);
+        assert_eq!(
+            w.shade_hit(&i, REF_RECURSION_LIMIT),
+            Colour::new(0.88066, 0.47583, 0.5355)
+        );
+    }
+
+    #[test]
+    fn emitter_lighting() {
+        let floor = Object {
+            shape: Shape::Plane,
+            transform: Matrix::identity(4).into(),
+            material: Material {
+                specular: 0.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let lamp = Object {
+            shape: Shape::Sphere,
+            transform: translate(0.0, 5.0, 0.0).into(),
+            material: Material {
+                emissive: Colour::white(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut w = World {
+            light: PointLight {
+                position: Float4::new_point(0.0, 10.0, 0.0),
+                colour: Colour::black(),
+            },
+            objects: vec![floor.clone(), lamp],
+            fog: None,
+            environment: None,
+        };
+        let r = Ray::new(Point3::new(0.0, 1.0, 0.0), Vector3::new(0.0, -1.0, 0.0));
+        let i = Intersection::new(&r, &floor, 1.0);
+        let lit = w.emitter_lighting(&i);
+        assert!(lit.0 .0[0] > 0.8 && lit.0 .0[0] <= 0.9);
+        assert_eq!(w.shade_hit(&i, REF_RECURSION_LIMIT), lit);
+
+        let blocker = Object {
+            shape: Shape::Sphere,
+            transform: (translate(0.0, 2.0, 0.0) * scale(3.0, 0.1, 3.0)).into(),
+            material: Material::default(),
+            ..Default::default()
+        };
+        w.objects.push(blocker);
+        assert_eq!(w.emitter_lighting(&i), Colour::black());
+    }
+
+    #[test]
+    fn path_colour() {
+        let w1 = World::default();
+        let r1 = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 1.0, 0.0));
+        let mut rng = Rng::new(1);
+        assert_eq!(w1.path_colour(&r1, 0, 8, &mut rng), Colour::black());
+
+        let lamp = Object {
+            shape: Shape::Sphere,
+            transform: Matrix::identity(4).into(),
+            material: Material {
+                diffuse: 0.0,
+                emissive: Colour::new(2.0, 1.0, 0.5),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let w2 = World {
+            objects: vec![lamp],
+            ..Default::default()
         };
-        let is5 = Intersections::new(vec![Intersection::new(&r5, &floor, 2f64.sqrt())]);
+        let r2 = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
         assert_eq!(
-            w5.shade_hit(is5.get_intersection_at(0), REF_RECURSION_LIMIT),
-            Colour::new(0.93642, 0.68642, 0.68642)
+            w2.path_colour(&r2, 0, 8, &mut rng),
+            Colour::new(2.0, 1.0, 0.5)
         );
     }
 
     #[test]
-    fn schlick() {
+    fn path_colour_furnace() {
+        // inside a closed diffuse emitter the radiance converges to E / (1 - albedo)
+        let shell = Object {
+            shape: Shape::Sphere,
+            transform: scale(10.0, 10.0, 10.0).into(),
+            material: Material {
+                diffuse: 0.5,
+                specular: 0.0,
+                emissive: Colour::new(0.5, 0.5, 0.5),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let w = World {
+            light: PointLight {
+                position: Float4::origin(),
+                colour: Colour::black(),
+            },
+            objects: vec![shell],
+            fog: None,
+            environment: None,
+        };
+        let r = Ray::new(Point3::origin(), Vector3::new(0.0, 0.0, 1.0));
+        let mut rng = Rng::new(7);
+        let samples = 500;
+        let total = (0..samples).fold(Colour::black(), |acc, _| {
+            acc + w.path_colour(&r, 0, 32, &mut rng)
+        });
+        let mean = total * (1.0 / samples as Float);
+        assert!((mean.0 .0[0] - 1.0).abs() < 0.05);
+        assert!((mean.0 .0[1] - 1.0).abs() < 0.05);
+        assert!((mean.0 .0[2] - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn refracted_colour_dispersion() {
+        let mut w = World::default();
+        w.objects[0].material.ambient = 1.0;
+        w.objects[0].material.pattern = Some(Pattern {
+            transform: Matrix::identity(4),
+            kind: PatternKind::TestLocation,
+            jitter: None,
+        });
+        w.objects[1].material.transparency = 1.0;
+        w.objects[1].material.refractive_index = 1.5;
+        let r = Ray::new(Point3::new(0.0, 0.2, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let refracted = |w: &World| {
+            let is = w.intersect(&r);
+            w.refracted_colour(&is.get_intersection_at(1), REF_RECURSION_LIMIT)
+        };
+        let plain = refracted(&w);
+
+        // an index that doesn't vary with wavelength recombines to the same colour
+        w.objects[1].material.dispersion = Some(Cauchy { a: 1.5, b: 0.0 });
+        assert_eq!(refracted(&w), plain);
+
+        w.objects[1].material.dispersion = Some(Cauchy { a: 1.4, b: 0.05 });
+        assert_ne!(refracted(&w), plain);
+    }
+
+    #[test]
+    fn refracted_colour_absorption() {
         let mut w = World::default();
-        let r = Ray {
-            origin: Float4::new_point(0.0, 0.0, -3.0),
-            direction: Float4::new_vector(0.0, -1.0 / 2f64.sqrt(), 1.0 / 2f64.sqrt()),
+        w.objects[0].material.transparency = 1.0;
+        w.objects[0].material.refractive_index = 1.0;
+        let r = Ray::new(Point3::new(0.0, 0.0, -5.0), Vector3::new(0.0, 0.0, 1.0));
+        let clear = w.intersect(&r);
+        let clear = w.refracted_colour(&clear.get_intersection_at(0), REF_RECURSION_LIMIT);
+
+        // the refracted ray crosses 0.5 units of glass before reaching the inner sphere
+        w.objects[0].material.absorption = Colour::new(0.5, 1.0, 0.0);
+        let tinted = w.intersect(&r);
+        let tinted = w.refracted_colour(&tinted.get_intersection_at(0), REF_RECURSION_LIMIT);
+        assert_eq!(
+            tinted,
+            clear * Colour::new(Float::exp(-0.25), Float::exp(-0.5), 1.0)
+        );
+    }
+
+    #[test]
+    fn absorbed_colour_miss() {
+        let w = World {
+            objects: vec![],
+            environment: Some(Environment {
+                background: Background::Solid(Colour::new(0.2, 0.4, 0.6)),
+                samples: 0,
+            }),
+            ..Default::default()
         };
+        let r = Ray::new(Point3::origin(), Vector3::new(0.0, 0.0, 1.0));
+        // an endless medium absorbs the red and green entirely
+        assert_eq!(
+            w.absorbed_colour_at(&r, Colour::new(0.5, 0.1, 0.0), REF_RECURSION_LIMIT),
+            Colour::new(0.0, 0.0, 0.6)
+        );
+    }
+
+    #[test]
+    fn total_internal_reflection_absorption() {
+        let glass = |absorption| World {
+            objects: vec![Object {
+                shape: Shape::Sphere,
+                transform: Matrix::identity(4).into(),
+                material: Material {
+                    reflective: 0.9,
+                    transparency: 0.9,
+                    refractive_index: 1.5,
+                    absorption,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let r = Ray::new(
+            Point3::new(0.0, 0.0, Float::sqrt(2.0) / 2.0),
+            Vector3::new(0.0, 1.0, 0.0),
+        );
+        let reflected = |w: &World| {
+            let is = w.intersect(&r);
+            w.reflected_colour(&is.get_intersection_at(1), REF_RECURSION_LIMIT)
+        };
+        let clear = reflected(&glass(Colour::black()));
+        let tinted = reflected(&glass(Colour::new(1.0, 1.0, 1.0)));
+        assert!((0..3).all(|i| tinted.0 .0[i] < clear.0 .0[i]));
+    }
+
+    #[test]
+    fn schlick() {
+        let mut w = World::default();
+        let r = Ray::new(
+            Point3::new(0.0, 0.0, -3.0),
+            Vector3::new(0.0, -1.0 / Float::sqrt(2.0), 1.0 / Float::sqrt(2.0)),
+        );
         let floor = Object {
             shape: Shape::Plane,
-            transform: translate(0.0, -1.0, 0.0),
+            transform: translate(0.0, -1.0, 0.0).into(),
             material: Material {
                 reflective: 0.5,
                 transparency: 0.5,
                 refractive_index: 1.5,
                 ..Default::default()
             },
+            ..Default::default()
         };
         w.objects.push(floor.clone());
         let ball = Object {
             shape: Shape::Plane,
-            transform: translate(0.0, -3.5, -0.5),
+            transform: translate(0.0, -3.5, -0.5).into(),
             material: Material {
                 colour: Colour::new(1.0, 0.0, 0.0),
                 ambient: 0.5,
                 ..Default::default()
             },
+            ..Default::default()
         };
         w.objects.push(ball);
-        let intersections = Intersections::new(vec![Intersection::new(&r, &floor, 2f64.sqrt())]);
+        let intersections =
+            Intersections::new(vec![Intersection::new(&r, &floor, Float::sqrt(2.0))]);
         assert_eq!(
-            w.shade_hit(intersections.get_intersection_at(0), REF_RECURSION_LIMIT),
-            Colour::new(0.93391, 0.69643, 0.69243)
+            w.shade_hit(&intersections.get_intersection_at(0), REF_RECURSION_LIMIT),
+            Colour::new(1.08530, 0.69643, 0.69243)
         );
     }
 }
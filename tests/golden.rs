@@ -0,0 +1,264 @@
+// Renders small reference scenes and compares them with the images stored in
+// `tests/golden`. Run with `UPDATE_GOLDEN=1` to re-bless them after an
+// intentional change to the output.
+
+use ray::camera::{Camera, Integrator};
+use ray::canvas::Canvas;
+use ray::colour::Colour;
+use ray::environment::{Background, Environment};
+use ray::float4::Float4;
+use ray::matrix::{scale, translate, view_transform, Matrix};
+use ray::medium::Medium;
+use ray::object::{Material, Object, PointLight, Shape};
+use ray::pattern::{Pattern, PatternKind};
+use ray::util::{consts::PI, Float};
+use ray::world::World;
+
+use std::path::{Path, PathBuf};
+
+const WIDTH: usize = 32;
+const HEIGHT: usize = 24;
+// largest difference allowed in any channel of any pixel
+const PIXEL_TOLERANCE: Float = 4.0 / 255.0;
+const MIN_PSNR: Float = 40.0;
+
+fn camera(width: usize, height: usize) -> Camera {
+    Camera::new(
+        width,
+        height,
+        PI / 3.0,
+        view_transform(
+            Float4::new_point(0.0, 1.5, -5.0),
+            Float4::new_point(0.0, 1.0, 0.0),
+            Float4::new_vector(0.0, 1.0, 0.0),
+        ),
+    )
+}
+
+fn floor(material: Material) -> Object {
+    Object {
+        shape: Shape::Plane,
+        transform: Matrix::identity(4).into(),
+        material,
+        ..Default::default()
+    }
+}
+
+fn sphere(transform: Matrix, material: Material) -> Object {
+    Object {
+        shape: Shape::Sphere,
+        transform: transform.into(),
+        material,
+        ..Default::default()
+    }
+}
+
+fn light() -> PointLight {
+    PointLight {
+        position: Float4::new_point(-10.0, 10.0, -10.0),
+        colour: Colour::white(),
+    }
+}
+
+fn patterns_and_reflections() -> World {
+    let checkered = Material {
+        reflective: 0.4,
+        specular: 0.0,
+        pattern: Some(Pattern {
+            kind: PatternKind::Checkers(Colour::white(), Colour::new(0.2, 0.2, 0.3)),
+            transform: Matrix::identity(4),
+            jitter: None,
+        }),
+        ..Default::default()
+    };
+    let striped = Material {
+        pattern: Some(Pattern {
+            kind: PatternKind::Stripe(Colour::new(1.0, 0.5, 0.0), Colour::new(0.1, 0.3, 0.9)),
+            transform: scale(0.2, 0.2, 0.2),
+            jitter: None,
+        }),
+        ..Default::default()
+    };
+    World {
+        light: light(),
+        objects: vec![
+            floor(checkered),
+            sphere(translate(-0.6, 1.0, 0.5), striped),
+            sphere(
+                translate(1.2, 0.5, -0.5) * scale(0.5, 0.5, 0.5),
+                Material {
+                    colour: Colour::new(0.1, 0.1, 0.1),
+                    reflective: 0.9,
+                    ..Default::default()
+                },
+            ),
+        ],
+        fog: None,
+        environment: None,
+    }
+}
+
+fn glass() -> World {
+    World {
+        light: light(),
+        objects: vec![
+            floor(Material {
+                pattern: Some(Pattern {
+                    kind: PatternKind::Ring(Colour::white(), Colour::new(0.8, 0.1, 0.1)),
+                    transform: scale(0.3, 0.3, 0.3),
+                    jitter: None,
+                }),
+                ..Default::default()
+            }),
+            sphere(
+                translate(0.0, 1.0, 0.0),
+                Material {
+                    colour: Colour::black(),
+                    ambient: 0.0,
+                    diffuse: 0.1,
+                    reflective: 0.9,
+                    transparency: 0.9,
+                    refractive_index: 1.5,
+                    absorption: Colour::new(0.0, 0.2, 0.4),
+                    ..Default::default()
+                },
+            ),
+        ],
+        fog: None,
+        environment: Some(Environment {
+            background: Background::Gradient {
+                horizon: Colour::new(0.9, 0.9, 1.0),
+                zenith: Colour::new(0.2, 0.4, 0.9),
+            },
+            samples: 0,
+        }),
+    }
+}
+
+fn fog() -> World {
+    World {
+        light: PointLight {
+            position: Float4::new_point(2.0, 6.0, 2.0),
+            colour: Colour::new(4.0, 4.0, 4.0),
+        },
+        objects: vec![
+            floor(Material::default()),
+            sphere(translate(0.0, 1.0, 0.0), Material::default()),
+        ],
+        fog: Some(Medium {
+            density: 0.08,
+            albedo: Colour::new(0.9, 0.9, 0.9),
+            anisotropy: 0.3,
+        }),
+        environment: None,
+    }
+}
+
+fn path_traced_lamp() -> World {
+    World {
+        light: PointLight {
+            position: Float4::origin(),
+            colour: Colour::black(),
+        },
+        objects: vec![
+            floor(Material {
+                colour: Colour::new(0.8, 0.8, 0.8),
+                ..Default::default()
+            }),
+            sphere(
+                translate(0.0, 1.0, 0.0),
+                Material {
+                    emissive: Colour::new(3.0, 2.5, 2.0),
+                    diffuse: 0.0,
+                    ..Default::default()
+                },
+            ),
+        ],
+        fog: None,
+        environment: None,
+    }
+}
+
+fn check(name: &str, world: World, camera: Camera) {
+    let golden = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{name}.ppm"));
+    let rendered = camera.render(world);
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        rendered.to_file(&golden).unwrap();
+        return;
+    }
+
+    let expected = Canvas::read_file(&golden).unwrap_or_else(|e| {
+        panic!(
+            "cannot read {}: {e}; run with UPDATE_GOLDEN=1 to create it",
+            golden.display()
+        )
+    });
+    assert_eq!(
+        (rendered.width, rendered.height),
+        (expected.width, expected.height),
+        "{name} has changed size"
+    );
+    // quantise the render the same way the golden image was
+    let actual = Canvas::from_ppm(&rendered.to_ppm_binary()).unwrap();
+    let result = actual.compare(&expected);
+
+    // the golden images are rendered in double precision, and single precision
+    // has to stay within the same bounds
+    if result.max_error > PIXEL_TOLERANCE || result.psnr < MIN_PSNR {
+        let out = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("golden");
+        std::fs::create_dir_all(&out).unwrap();
+        actual
+            .to_file(&out.join(format!("{name}-actual.ppm")))
+            .unwrap();
+        actual
+            .heatmap(&expected, PIXEL_TOLERANCE)
+            .to_file(&out.join(format!("{name}-diff.ppm")))
+            .unwrap();
+        panic!(
+            "{name} differs from its golden image: RMSE {:.5}, PSNR {:.2} dB, max error {:.5}, \
+             SSIM {:.4}; see {}",
+            result.mse.sqrt(),
+            result.psnr,
+            result.max_error,
+            result.ssim,
+            out.display()
+        );
+    }
+}
+
+#[test]
+fn default_world() {
+    check("default_world", World::default(), camera(WIDTH, HEIGHT));
+}
+
+#[test]
+fn patterns_and_reflections_scene() {
+    check(
+        "patterns_and_reflections",
+        patterns_and_reflections(),
+        camera(WIDTH, HEIGHT),
+    );
+}
+
+#[test]
+fn glass_scene() {
+    check("glass", glass(), camera(WIDTH, HEIGHT));
+}
+
+#[test]
+fn fog_scene() {
+    // ray marching is slow enough to warrant a smaller image
+    check("fog", fog(), camera(WIDTH / 2, HEIGHT / 2));
+}
+
+#[test]
+fn path_traced_lamp_scene() {
+    let camera = camera(WIDTH / 2, HEIGHT / 2).with_integrator(Integrator::PathTracer {
+        samples: 8,
+        max_depth: 4,
+    });
+    check("path_traced_lamp", path_traced_lamp(), camera);
+}